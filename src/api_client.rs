@@ -1,40 +1,59 @@
 use anyhow::anyhow;
 use crossbeam_channel::{unbounded, Receiver};
-use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
-use reqwest::Client;
+use futures::future::join_all;
+use indicatif::HumanDuration;
+use reqwest::{Client, Url};
 use serde_json::json;
 use solana_sdk::pubkey::Pubkey;
+use std::io::Write;
 use std::thread;
 use std::time::{Duration, Instant};
 
 use crate::api_models::{
-    ErrorResponse, JobResponse, JobStatus, JobVerificationResponse, VerifyResponse,
+    ErrorResponse, JobProgress, JobResponse, JobStatus, JobVerificationResponse, VerifyResponse,
+};
+use crate::{
+    new_spinner, OutputFormat, PollOptions, WorkspaceProgramResult, DONE, FAILED, WAITING,
 };
 
 // URL for the remote server
 pub const REMOTE_SERVER_URL: &str = "https://verify.osec.io";
 
+// Validate a user supplied remote server URL
+pub fn parse_remote_url(remote_url: &str) -> anyhow::Result<String> {
+    let url = Url::parse(remote_url)
+        .map_err(|e| anyhow!("Invalid remote server URL {}: {}", remote_url, e))?;
+    if url.scheme() != "https" {
+        return Err(anyhow!(
+            "Invalid remote server URL {}: only https:// URLs are supported",
+            remote_url
+        ));
+    }
+    Ok(remote_url.trim_end_matches('/').to_string())
+}
+
 fn loading_animation(receiver: Receiver<bool>) {
     let started = Instant::now();
-    let spinner_style =
-        ProgressStyle::with_template("[{elapsed_precise}] {prefix:.bold.dim} {spinner} {wide_msg}")
-            .unwrap()
-            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ");
-
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(spinner_style);
-    pb.set_message("Request sent. Awaiting server response. This may take a moment... ⏳");
+    let pb = new_spinner(format!(
+        "Request sent. Awaiting server response. This may take a moment... {}",
+        WAITING
+    ));
     loop {
         match receiver.try_recv() {
             Ok(result) => {
                 if result {
                     pb.finish_with_message(format!(
-                        "✅ Process completed. (Done in {})\n",
+                        "{} Process completed. (Done in {})\n",
+                        DONE,
                         HumanDuration(started.elapsed())
                     ));
                 } else {
-                    pb.finish_with_message("❌ Request processing failed.");
-                    println!("❌ Time elapsed : {}", HumanDuration(started.elapsed()));
+                    pb.finish_with_message(format!("{} Request processing failed.", FAILED));
+                    println!(
+                        "{} Time elapsed : {}",
+                        FAILED,
+                        HumanDuration(started.elapsed())
+                    );
                 }
                 break;
             }
@@ -50,6 +69,7 @@ fn loading_animation(receiver: Receiver<bool>) {
 // Send a job to the remote server
 #[allow(clippy::too_many_arguments)]
 pub async fn send_job_to_remote(
+    remote_url: &str,
     repo_url: &str,
     commit_hash: &Option<String>,
     program_id: &Pubkey,
@@ -58,109 +78,500 @@ pub async fn send_job_to_remote(
     relative_mount_path: String,
     base_image: Option<String>,
     cargo_args: Vec<String>,
+    poll_options: &PollOptions,
+    output: OutputFormat,
 ) -> anyhow::Result<()> {
     let client = Client::builder()
-        .timeout(Duration::from_secs(18000))
+        .timeout(Duration::from_secs(poll_options.job_timeout))
         .build()?;
 
     // Send the POST request
     let response = client
-        .post(format!("{}/verify", REMOTE_SERVER_URL))
-        .json(&json!({
-            "repository": repo_url,
-            "commit_hash": commit_hash,
-            "program_id": program_id.to_string(),
-            "lib_name": library_name,
-            "bpf_flag": bpf_flag,
-            "mount_path":  if relative_mount_path.is_empty() {
-                None
-            } else {
-                Some(relative_mount_path)
-            },
-            "base_image": base_image,
-            "cargo_args": cargo_args,
-        }))
+        .post(format!("{}/verify", remote_url))
+        .json(&job_request(
+            repo_url,
+            commit_hash,
+            program_id,
+            library_name,
+            bpf_flag,
+            &relative_mount_path,
+            &base_image,
+            &cargo_args,
+        ))
         .send()
         .await?;
 
     if response.status().is_success() {
         let status_response: VerifyResponse = response.json().await?;
-        println!("Verification request sent. ✅");
-        println!("Verification in progress... ⏳");
-        // Span new thread for polling the server for status
-        // Create a channel for communication between threads
-        let (sender, receiver) = unbounded();
-
-        let handle = thread::spawn(move || loading_animation(receiver));
-        // Poll the server for status
-        loop {
-            let status = check_job_status(&client, &status_response.request_id).await?;
-            match status.status {
-                JobStatus::InProgress => {
-                    thread::sleep(Duration::from_secs(10));
-                }
-                JobStatus::Completed => {
-                    let _ = sender.send(true);
-                    handle.join().unwrap();
-                    let status_response = status.respose.unwrap();
-                    println!("Program {} has been successfully verified. ✅", program_id);
-                    println!("\nThe provided GitHub build matches the on-chain hash:");
-                    println!("On Chain Hash: {}", status_response.on_chain_hash.as_str());
-                    println!(
-                        "Executable Hash: {}",
-                        status_response.executable_hash.as_str()
-                    );
-                    println!("Repo URL: {}", status_response.repo_url.as_str());
-                    break;
-                }
-                JobStatus::Failed => {
-                    let _ = sender.send(false);
-
-                    handle.join().unwrap();
-                    let status_response: JobVerificationResponse = status.respose.unwrap();
-                    println!("Program {} has not been verified. ❌", program_id);
-                    eprintln!("Error message: {}", status_response.message.as_str());
-                    break;
-                }
-                JobStatus::Unknown => {
-                    let _ = sender.send(false);
-                    handle.join().unwrap();
-                    println!("Program {} has not been verified. ❌", program_id);
-                    break;
-                }
+        match output {
+            OutputFormat::Json => print_progress(&JobProgress::Submitted {
+                request_id: status_response.request_id.clone(),
+            })?,
+            OutputFormat::Text => {
+                println!("Verification request sent. {}", DONE);
+                println!("Request ID: {}", status_response.request_id);
+                println!(
+                    "If you get disconnected, resume with: solana-verify resume-remote-job {}",
+                    status_response.request_id
+                );
+                println!("Verification in progress... {}", WAITING);
             }
         }
-
-        Ok(())
+        wait_for_job(
+            &client,
+            remote_url,
+            &status_response.request_id,
+            &format!("Program {}", program_id),
+            poll_options,
+            output,
+        )
+        .await
     } else if response.status() == 409 {
         let response = response.json::<ErrorResponse>().await?;
-        eprintln!("Error: {}", response.error.as_str());
-        Ok(())
+        Err(anyhow!(
+            "The remote server rejected the job: {}",
+            response.error
+        ))
     } else {
         eprintln!("Encountered an error while attempting to send the job to remote");
         Err(anyhow!("{:?}", response.text().await?))?
     }
 }
 
-async fn check_job_status(client: &Client, request_id: &str) -> anyhow::Result<JobResponse> {
+// Body of the request to verify one program
+#[allow(clippy::too_many_arguments)]
+fn job_request(
+    repo_url: &str,
+    commit_hash: &Option<String>,
+    program_id: &Pubkey,
+    library_name: &Option<String>,
+    bpf_flag: bool,
+    relative_mount_path: &str,
+    base_image: &Option<String>,
+    cargo_args: &[String],
+) -> serde_json::Value {
+    json!({
+        "repository": repo_url,
+        "commit_hash": commit_hash,
+        "program_id": program_id.to_string(),
+        "lib_name": library_name,
+        "bpf_flag": bpf_flag,
+        "mount_path":  if relative_mount_path.is_empty() {
+            None
+        } else {
+            Some(relative_mount_path)
+        },
+        "base_image": base_image,
+        "cargo_args": cargo_args,
+    })
+}
+
+// Send one job per program of a workspace to the remote server and wait for all of them. The
+// server verifies a single program per job, so the jobs are all sent before waiting for any
+// of them, and run side by side on the server
+#[allow(clippy::too_many_arguments)]
+pub async fn send_jobs_to_remote(
+    remote_url: &str,
+    repo_url: &str,
+    commit_hash: &Option<String>,
+    programs: &[(String, Pubkey)],
+    bpf_flag: bool,
+    relative_mount_path: &str,
+    base_image: &Option<String>,
+    cargo_args: &[String],
+    poll_options: &PollOptions,
+    output: OutputFormat,
+) -> anyhow::Result<Vec<WorkspaceProgramResult>> {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(poll_options.job_timeout))
+        .build()?;
+
+    let mut jobs = vec![];
+    for (library_name, program_id) in programs {
+        let response = client
+            .post(format!("{}/verify", remote_url))
+            .json(&job_request(
+                repo_url,
+                commit_hash,
+                program_id,
+                &Some(library_name.clone()),
+                bpf_flag,
+                relative_mount_path,
+                base_image,
+                cargo_args,
+            ))
+            .send()
+            .await?;
+        let request_id = if response.status().is_success() {
+            let status_response: VerifyResponse = response.json().await?;
+            if output == OutputFormat::Text {
+                println!(
+                    "Sent {} ({}), request ID: {}",
+                    library_name, program_id, status_response.request_id
+                );
+            }
+            Ok(status_response.request_id)
+        } else if response.status() == 409 {
+            Err(response.json::<ErrorResponse>().await?.error)
+        } else {
+            Err(format!("{:?}", response.text().await?))
+        };
+        jobs.push((library_name, program_id, request_id));
+    }
+
+    let spinner = (output == OutputFormat::Text).then(|| {
+        let spinner = new_spinner(format!("Verification in progress... {}", WAITING));
+        spinner.enable_steady_tick(Duration::from_millis(100));
+        spinner
+    });
+    // The jobs are polled side by side, so the job timeout bounds the wait for all of them
+    let started = Instant::now();
+    let polls = jobs.iter().map(|(_, _, request_id)| async {
+        match request_id {
+            // The results are printed together once all jobs are done
+            Ok(request_id) => poll_job_status(
+                &client,
+                remote_url,
+                request_id,
+                poll_options,
+                OutputFormat::Text,
+                started,
+            )
+            .await
+            .map_err(|e| e.to_string()),
+            Err(e) => Err(e.clone()),
+        }
+    });
+    let statuses = tokio::select! {
+        statuses = join_all(polls) => statuses,
+        _ = tokio::signal::ctrl_c() => {
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            let request_ids = jobs
+                .iter()
+                .filter_map(|(library_name, _, request_id)| {
+                    let request_id = request_id.as_ref().ok()?;
+                    Some(format!("{}: {}", library_name, request_id))
+                })
+                .collect::<Vec<_>>();
+            return Err(anyhow!(
+                "Remote verification interrupted. The jobs keep running on the remote server, resume waiting for one with `solana-verify resume-remote-job <request ID>` or cancel it with `solana-verify cancel-remote-job <request ID>`. Request IDs:\n{}",
+                request_ids.join("\n")
+            ));
+        }
+    };
+    let mut results = vec![];
+    for ((library_name, program_id, _), status) in jobs.into_iter().zip(statuses) {
+        results.push(match status {
+            Ok(JobResponse {
+                status: JobStatus::Completed,
+                respose: Some(response),
+            }) => WorkspaceProgramResult {
+                library_name: library_name.clone(),
+                program_id: program_id.to_string(),
+                executable_hash: Some(response.executable_hash),
+                on_chain_hash: Some(response.on_chain_hash),
+                verified: true,
+                error: None,
+            },
+            Ok(status) => WorkspaceProgramResult {
+                library_name: library_name.clone(),
+                program_id: program_id.to_string(),
+                executable_hash: None,
+                on_chain_hash: None,
+                verified: false,
+                error: status
+                    .respose
+                    .map(|response| response.message.trim().to_string())
+                    .filter(|message| !message.is_empty()),
+            },
+            Err(e) => WorkspaceProgramResult {
+                library_name: library_name.clone(),
+                program_id: program_id.to_string(),
+                executable_hash: None,
+                on_chain_hash: None,
+                verified: false,
+                error: Some(e),
+            },
+        });
+    }
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    Ok(results)
+}
+
+// Resume polling a job that was previously sent to the remote server
+pub async fn resume_remote_job(
+    remote_url: &str,
+    request_id: &str,
+    poll_options: &PollOptions,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(poll_options.job_timeout))
+        .build()?;
+    if output == OutputFormat::Text {
+        println!("Resuming verification job {}... {}", request_id, WAITING);
+    }
+    wait_for_job(
+        &client,
+        remote_url,
+        request_id,
+        &format!("The program of job {}", request_id),
+        poll_options,
+        output,
+    )
+    .await
+}
+
+// Print one line of JSON progress, for `--output json`
+fn print_progress(progress: &JobProgress) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string(progress)?);
+    Ok(())
+}
+
+// Wait for the job to finish and print its result as JSON lines, without a spinner
+async fn wait_for_job_json(
+    client: &Client,
+    remote_url: &str,
+    request_id: &str,
+    poll_options: &PollOptions,
+) -> anyhow::Result<()> {
+    let status = tokio::select! {
+        status = poll_job_status(client, remote_url, request_id, poll_options, OutputFormat::Json, Instant::now()) => status?,
+        _ = tokio::signal::ctrl_c() => {
+            return Err(anyhow!(
+                "Remote verification interrupted. The job keeps running on the remote server, resume with: solana-verify resume-remote-job {}",
+                request_id
+            ));
+        }
+    };
+    let progress = match (status.status, status.respose) {
+        (JobStatus::Completed, Some(response)) => JobProgress::Completed {
+            request_id: request_id.to_string(),
+            verified: true,
+            on_chain_hash: response.on_chain_hash,
+            executable_hash: response.executable_hash,
+            repo_url: response.repo_url,
+        },
+        (JobStatus::Completed, None) => {
+            return Err(anyhow!(
+                "The server reported job {} as completed without its result",
+                request_id
+            ))
+        }
+        (_, response) => JobProgress::Failed {
+            request_id: request_id.to_string(),
+            verified: false,
+            message: response
+                .map(|response| response.message.trim().to_string())
+                .filter(|message| !message.is_empty()),
+        },
+    };
+    print_progress(&progress)?;
+    // The result is on stdout either way, the exit status tells a failure apart without parsing it
+    match progress {
+        JobProgress::Failed {
+            message: Some(message),
+            ..
+        } => Err(anyhow!("Remote job {} failed: {}", request_id, message)),
+        JobProgress::Failed { message: None, .. } => Err(anyhow!(
+            "Remote job {} failed without a message from the server",
+            request_id
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Wait for the job to finish while showing a spinner, then report the result
+async fn wait_for_job(
+    client: &Client,
+    remote_url: &str,
+    request_id: &str,
+    program: &str,
+    poll_options: &PollOptions,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    if output == OutputFormat::Json {
+        return wait_for_job_json(client, remote_url, request_id, poll_options).await;
+    }
+
+    // Span new thread for polling the server for status
+    // Create a channel for communication between threads
+    let (sender, receiver) = unbounded();
+
+    let handle = thread::spawn(move || loading_animation(receiver));
+    // Poll the server for status, giving the user the chance to cancel the job on Ctrl-C
+    let status = tokio::select! {
+        status = poll_job_status(client, remote_url, request_id, poll_options, output, Instant::now()) => status,
+        _ = tokio::signal::ctrl_c() => {
+            let _ = sender.send(false);
+            handle.join().unwrap();
+            return handle_interrupted_job(remote_url, request_id).await;
+        }
+    };
+    let status = match status {
+        Ok(status) => status,
+        Err(e) => {
+            let _ = sender.send(false);
+            handle.join().unwrap();
+            return Err(e);
+        }
+    };
+
+    match status.status {
+        JobStatus::Completed => {
+            let _ = sender.send(true);
+            handle.join().unwrap();
+            let status_response = status.respose.ok_or_else(|| {
+                anyhow!(
+                    "The server reported job {} as completed without its result",
+                    request_id
+                )
+            })?;
+            println!("{} has been successfully verified. {}", program, DONE);
+            println!("\nThe provided GitHub build matches the on-chain hash:");
+            println!("On Chain Hash: {}", status_response.on_chain_hash.as_str());
+            println!(
+                "Executable Hash: {}",
+                status_response.executable_hash.as_str()
+            );
+            println!("Repo URL: {}", status_response.repo_url.as_str());
+        }
+        JobStatus::Failed | JobStatus::Unknown | JobStatus::InProgress => {
+            let _ = sender.send(false);
+            handle.join().unwrap();
+            println!("{} has not been verified. {}", program, FAILED);
+            // The server doesn't always explain why, e.g. when the job status is unknown
+            let message = status
+                .respose
+                .as_ref()
+                .map(|response| response.message.trim())
+                .filter(|message| !message.is_empty());
+            return Err(match message {
+                Some(message) => anyhow!("Remote job {} failed: {}", request_id, message),
+                None => anyhow!(
+                    "Remote job {} failed without a message from the server",
+                    request_id
+                ),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// Poll the server until the job is no longer in progress, or the job timeout has elapsed since
+// `started`. With JSON output, every poll of a job in progress is reported
+async fn poll_job_status(
+    client: &Client,
+    remote_url: &str,
+    request_id: &str,
+    poll_options: &PollOptions,
+    output: OutputFormat,
+    started: Instant,
+) -> anyhow::Result<JobResponse> {
+    let deadline = started + Duration::from_secs(poll_options.job_timeout);
+    loop {
+        let status = check_job_status(client, remote_url, request_id).await?;
+        match status.status {
+            JobStatus::InProgress => {
+                if output == OutputFormat::Json {
+                    print_progress(&JobProgress::InProgress {
+                        request_id: request_id.to_string(),
+                        elapsed_secs: started.elapsed().as_secs(),
+                    })?;
+                }
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Err(anyhow!(
+                        "Remote job {} did not finish within {} seconds. It may still be running on the server, resume waiting with: solana-verify resume-remote-job {}",
+                        request_id,
+                        poll_options.job_timeout,
+                        request_id
+                    ));
+                }
+                tokio::time::sleep(remaining.min(Duration::from_secs(poll_options.poll_interval)))
+                    .await;
+            }
+            _ => return Ok(status),
+        }
+    }
+}
+
+// Ask the user whether the interrupted job should also be cancelled on the server
+async fn handle_interrupted_job(remote_url: &str, request_id: &str) -> anyhow::Result<()> {
+    print!(
+        "\nInterrupted. Do you want to cancel the remote job {}? [y/N] ",
+        request_id
+    );
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if answer.trim().eq_ignore_ascii_case("y") {
+        cancel_remote_job(remote_url, request_id).await?;
+    } else {
+        println!(
+            "The job will keep running on the remote server. Request ID: {}",
+            request_id
+        );
+    }
+    Err(anyhow!("Remote verification interrupted"))
+}
+
+// Cancel a job on the remote server
+pub async fn cancel_remote_job(remote_url: &str, request_id: &str) -> anyhow::Result<()> {
+    let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+    let response = client
+        .delete(format!("{}/job/{}", remote_url, request_id))
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        println!("Remote job {} has been cancelled. {}", request_id, DONE);
+        let message = response.text().await?;
+        if !message.is_empty() {
+            println!("Server response: {}", message);
+        }
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Encountered an error while attempting to cancel job {} : {:?}",
+            request_id,
+            response.text().await?
+        ))?
+    }
+}
+
+// Check the status of a job once. This returns as soon as the server answers, so the polling
+// interval is entirely up to the caller
+async fn check_job_status(
+    client: &Client,
+    remote_url: &str,
+    request_id: &str,
+) -> anyhow::Result<JobResponse> {
     // Get /job/:id
     let response = client
-        .get(&format!("{}/job/{}", REMOTE_SERVER_URL, request_id))
+        .get(format!("{}/job/{}", remote_url, request_id))
         .send()
-        .await
-        .unwrap();
+        .await?;
 
     if response.status().is_success() {
         // Parse the response
-        let response: JobVerificationResponse = response.json().await?;
+        let response: JobVerificationResponse = response.json().await.map_err(|e| {
+            anyhow!(
+                "Failed to parse the status of job {} from the server: {}",
+                request_id,
+                e
+            )
+        })?;
         match response.status {
-            JobStatus::InProgress => {
-                thread::sleep(Duration::from_secs(5));
-                Ok(JobResponse {
-                    status: JobStatus::InProgress,
-                    respose: None,
-                })
-            }
+            JobStatus::InProgress => Ok(JobResponse {
+                status: JobStatus::InProgress,
+                respose: None,
+            }),
             JobStatus::Completed => Ok(JobResponse {
                 status: JobStatus::Completed,
                 respose: Some(response),
@@ -181,3 +592,128 @@ async fn check_job_status(client: &Client, request_id: &str) -> anyhow::Result<J
         ))?
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+
+    // Answer every request with the JSON body that `respond` returns for its request line, and
+    // return the URL of the server
+    fn stub_server(respond: fn(&str) -> &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut reader = BufReader::new(stream.unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                // Skip the headers and the body of the request
+                let mut content_length = 0;
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                        content_length = length.trim().parse().unwrap();
+                    }
+                    line.clear();
+                }
+                reader.read_exact(&mut vec![0; content_length]).unwrap();
+                let body = respond(&request_line);
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn check_job_status_does_not_block() {
+        let url = stub_server(|_| r#"{"status":"in_progress"}"#);
+        let started = Instant::now();
+        let status = check_job_status(&Client::new(), &url, "job").await.unwrap();
+        assert!(matches!(status.status, JobStatus::InProgress));
+        let poll_interval = Duration::from_secs(PollOptions::default().poll_interval);
+        assert!(started.elapsed() < poll_interval / 10);
+    }
+
+    #[tokio::test]
+    async fn jobs_share_the_job_timeout() {
+        let url = stub_server(|request_line| {
+            if request_line.starts_with("POST") {
+                r#"{"status":"in_progress","request_id":"job","message":""}"#
+            } else {
+                r#"{"status":"in_progress"}"#
+            }
+        });
+        let programs = [
+            ("first".to_string(), Pubkey::new_unique()),
+            ("second".to_string(), Pubkey::new_unique()),
+        ];
+        let poll_options = PollOptions {
+            poll_interval: 1,
+            job_timeout: 1,
+        };
+        let started = Instant::now();
+        let results = send_jobs_to_remote(
+            &url,
+            "https://github.com/example/repo",
+            &None,
+            &programs,
+            false,
+            "",
+            &None,
+            &[],
+            &poll_options,
+            OutputFormat::Json,
+        )
+        .await
+        .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| !result.verified));
+        // Polled one after another, each job would wait for the whole timeout
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    fn failed_job(request_line: &str) -> &'static str {
+        if request_line.starts_with("POST") {
+            r#"{"status":"in_progress","request_id":"job","message":""}"#
+        } else {
+            r#"{"status":"failed","message":"Hash mismatch"}"#
+        }
+    }
+
+    async fn send_failed_job(output: OutputFormat) -> anyhow::Result<()> {
+        send_job_to_remote(
+            &stub_server(failed_job),
+            "https://github.com/example/repo",
+            &None,
+            &Pubkey::new_unique(),
+            &None,
+            false,
+            String::new(),
+            None,
+            vec![],
+            &PollOptions::default(),
+            output,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn failed_job_is_an_error() {
+        let error = send_failed_job(OutputFormat::Text).await.unwrap_err();
+        assert!(error.to_string().contains("Hash mismatch"));
+    }
+
+    #[tokio::test]
+    async fn failed_job_is_an_error_with_json_output() {
+        let error = send_failed_job(OutputFormat::Json).await.unwrap_err();
+        assert!(error.to_string().contains("Hash mismatch"));
+    }
+}
@@ -49,8 +49,38 @@ pub enum JobStatus {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JobVerificationResponse {
     pub status: JobStatus,
+    // A failed or unknown job may come without a message or any of the hashes
+    #[serde(default)]
     pub message: String,
+    #[serde(default)]
     pub on_chain_hash: String,
+    #[serde(default)]
     pub executable_hash: String,
+    #[serde(default)]
     pub repo_url: String,
 }
+
+/// Progress of a remote job, printed as one JSON object per line with `--output json`
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum JobProgress {
+    Submitted {
+        request_id: String,
+    },
+    InProgress {
+        request_id: String,
+        elapsed_secs: u64,
+    },
+    Completed {
+        request_id: String,
+        verified: bool,
+        on_chain_hash: String,
+        executable_hash: String,
+        repo_url: String,
+    },
+    Failed {
+        request_id: String,
+        verified: bool,
+        message: Option<String>,
+    },
+}
@@ -0,0 +1,44 @@
+use anyhow::anyhow;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Defaults for command line options, read from `~/.config/solana-verify/config.toml` or the file
+/// passed with `--config`. Options given on the command line take precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    /// Default for `--url`, e.g. `mainnet` or the URL of an RPC provider
+    pub rpc_url: Option<String>,
+    /// Default for `--base-image`
+    pub base_image: Option<String>,
+    /// Default for `--commitment`
+    pub commitment: Option<String>,
+}
+
+impl ConfigFile {
+    /// Location of the config file that is read when no `--config` is passed
+    pub fn default_path() -> Option<PathBuf> {
+        std::env::var_os("HOME").map(|home| {
+            PathBuf::from(home)
+                .join(".config")
+                .join("solana-verify")
+                .join("config.toml")
+        })
+    }
+
+    /// Reads the config file at `path`, or at the default path if none is given. Only a missing
+    /// default config file is fine; it is then as if it were empty.
+    pub fn load(path: Option<&str>) -> anyhow::Result<Self> {
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => match Self::default_path().filter(|path| path.exists()) {
+                Some(path) => path,
+                None => return Ok(Self::default()),
+            },
+        };
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read config file {}: {}", path.display(), e))?;
+        toml::from_str(&contents)
+            .map_err(|e| anyhow!("Invalid config file {}: {}", path.display(), e))
+    }
+}
@@ -0,0 +1,69 @@
+use solana_client::client_error::ClientError;
+use solana_sdk::pubkey::Pubkey;
+use thiserror::Error;
+
+/// Errors returned by the verify functions, so library users can match on what went wrong
+#[derive(Debug, Error)]
+pub enum VerifyError {
+    /// The executable doesn't match the hash it was verified against
+    #[error("{reason}: expected {expected}, got {actual}")]
+    HashMismatch {
+        expected: String,
+        actual: String,
+        reason: String,
+    },
+    /// The on-chain program can't be upgraded by the expected authority
+    #[error(
+        "Upgrade authority mismatch: expected {expected}, got {}",
+        .actual.map_or("none".to_string(), |actual| actual.to_string())
+    )]
+    AuthorityMismatch {
+        expected: Pubkey,
+        actual: Option<Pubkey>,
+    },
+    /// The program has been closed, so there is no program data left to verify against
+    #[error("Program {0} appears to be closed or has no data")]
+    ProgramClosed(Pubkey),
+    /// The account to read program data from doesn't exist
+    #[error("Account {0} not found")]
+    AccountNotFound(Pubkey),
+    /// A docker command could not be run
+    #[error("Docker failed: {0}")]
+    DockerFailed(String),
+    /// The build inside the container exited with an error
+    #[error("Build failed with {status}. Last lines of the build log:\n{log}")]
+    BuildFailed { status: String, log: String },
+    /// The build produced something other than an SBF program, e.g. a library for the host
+    #[error("Built artifact is not a valid SBF program: {path}: {reason}")]
+    InvalidExecutable { path: String, reason: String },
+    /// An RPC request failed
+    #[error(transparent)]
+    RpcError(Box<ClientError>),
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl From<ClientError> for VerifyError {
+    fn from(e: ClientError) -> Self {
+        VerifyError::RpcError(Box::new(e))
+    }
+}
+
+impl From<std::io::Error> for VerifyError {
+    fn from(e: std::io::Error) -> Self {
+        VerifyError::Other(e.into())
+    }
+}
+
+impl From<anyhow::Error> for VerifyError {
+    // Unwrap errors that were passed through `anyhow` so they can still be matched on
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<VerifyError>() {
+            Ok(e) => e,
+            Err(e) => match e.downcast::<ClientError>() {
+                Ok(e) => e.into(),
+                Err(e) => VerifyError::Other(e),
+            },
+        }
+    }
+}
@@ -0,0 +1,4878 @@
+use anyhow::anyhow;
+use cargo_lock::Lockfile;
+use cargo_toml::Manifest;
+use chrono::{SecondsFormat, Utc};
+use clap::{Args, ValueEnum};
+use goblin::elf::{
+    header::{machine_to_str, EM_BPF},
+    section_header::{SHF_ALLOC, SHT_NOBITS},
+    Elf,
+};
+use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
+use log::{debug, error, info, log_enabled, warn, Level};
+use rand::Rng;
+use reqwest::Url;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_cli_config::{Config, CONFIG_FILE};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_client::{RpcClient, RpcClientConfig},
+    rpc_config::RpcAccountInfoConfig,
+    rpc_request::RpcError,
+};
+use solana_sdk::{
+    account::Account,
+    bpf_loader, bpf_loader_deprecated,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+};
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    io::{BufRead, BufReader, Read},
+    path::{Component, Path, PathBuf},
+    process::{ExitStatus, Stdio},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+pub mod api_client;
+pub mod api_models;
+pub mod config_file;
+pub mod error;
+pub mod image_config;
+pub mod report;
+pub mod rpc_sender;
+use error::VerifyError;
+use image_config::IMAGE_MAP;
+use report::{ReportAttestation, VerificationReport};
+use rpc_sender::HeaderSender;
+
+use crate::api_client::{send_job_to_remote, send_jobs_to_remote};
+
+const MAINNET_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+
+/// Number of lines of the build log included in the error when a build fails
+const BUILD_LOG_TAIL_LINES: usize = 20;
+
+/// Number of times a clone that failed on a network error is retried
+const GIT_CLONE_RETRIES: u32 = 3;
+/// Where the `--target-dir` is mounted in the build container
+const CONTAINER_TARGET_DIR: &str = "/verify-target";
+/// Printed in place of the working directory of an image that `--print-command` doesn't pull
+const WORKDIR_PLACEHOLDER: &str = "<workdir>";
+
+/// Set with `--quiet` to only print errors and results
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Only print errors and results from now on, e.g. when a script captures the output
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Set with `--output json`, so that stdout holds nothing but the JSON result
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Print status lines to stderr from now on, leaving stdout to the JSON result
+pub fn set_json_output(json: bool) {
+    JSON_OUTPUT.store(json, Ordering::Relaxed);
+}
+
+pub fn is_json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// Set by commands whose stdout is a bare result, like a hash, so that scripts can read it
+static STATUS_ON_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Print status lines to stderr from now on, leaving stdout to the result of the command
+pub fn set_status_on_stderr(on_stderr: bool) {
+    STATUS_ON_STDERR.store(on_stderr, Ordering::Relaxed);
+}
+
+/// Prints a line about the progress of a command, unless `--quiet` was given. It goes to stdout,
+/// or to stderr with `--output json` and for commands that print a bare result
+pub fn print_status(message: impl std::fmt::Display) {
+    if is_quiet() {
+        return;
+    }
+    if is_json_output() || STATUS_ON_STDERR.load(Ordering::Relaxed) {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+/// Set with `--no-color`, or when stdout isn't a terminal, to print plain ASCII instead of emoji
+static PLAIN_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Print the plain ASCII fallbacks of `Emoji` from now on, e.g. when the output goes to a log file
+pub fn set_plain_output(plain: bool) {
+    PLAIN_OUTPUT.store(plain, Ordering::Relaxed);
+}
+
+pub fn is_plain_output() -> bool {
+    PLAIN_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// An emoji printed with a result, and the plain ASCII printed instead with `set_plain_output`
+#[derive(Clone, Copy, Debug)]
+pub struct Emoji(pub &'static str, pub &'static str);
+
+impl std::fmt::Display for Emoji {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(if is_plain_output() { self.1 } else { self.0 })
+    }
+}
+
+pub const DONE: Emoji = Emoji("✅", "[ok]");
+pub const FAILED: Emoji = Emoji("❌", "[failed]");
+pub const WAITING: Emoji = Emoji("⏳", "");
+pub const WARNING: Emoji = Emoji("⚠️ ", "[warning]");
+
+/// Number of bytes requested per call when account data has to be read in chunks
+pub const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Largest account read by default, well above the 10 MiB that a program can take up
+pub const DEFAULT_MAX_ACCOUNT_SIZE: usize = 16 * 1024 * 1024;
+
+/// Loader v4 is not yet part of the pinned solana-sdk, so its ID and state layout are mirrored here
+pub mod loader_v4 {
+    solana_sdk::declare_id!("LoaderV411111111111111111111111111111111111");
+
+    /// Size of the `LoaderV4State` header (slot, authority address, status) preceding the program code
+    pub const PROGRAM_DATA_OFFSET: usize = 48;
+
+    /// `LoaderV4Status` of a program that can no longer be upgraded
+    pub const STATUS_FINALIZED: u64 = 2;
+}
+
+pub fn get_network(network_str: &str) -> &str {
+    match network_str {
+        "devnet" | "dev" | "d" => "https://api.devnet.solana.com",
+        "mainnet" | "main" | "m" | "mainnet-beta" => "https://api.mainnet-beta.solana.com",
+        "testnet" | "test" | "t" => "https://api.testnet.solana.com",
+        "localnet" | "localhost" | "l" | "local" => "http://localhost:8899",
+        _ => network_str,
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable output
+    Text,
+    /// A single machine-readable JSON object
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerificationResult {
+    pub program_id: String,
+    pub executable_hash: String,
+    pub on_chain_hash: String,
+    pub verified: bool,
+    /// Toolchain of the container the executable was built in, if it was built locally
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toolchain: Option<Toolchain>,
+    /// The build as a single shell command, if it was built locally
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_command: Option<String>,
+    /// Size of the executable without zero padding, the part that is hashed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub executable_size: Option<usize>,
+    /// Size without zero padding of the program the executable was compared with, if it was read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_chain_size: Option<usize>,
+    /// Upgrade authority of the on-chain program, if it is upgradeable and not immutable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upgrade_authority: Option<String>,
+    /// Slot at which the on-chain program was last deployed, if its loader records it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_deployed_slot: Option<u64>,
+}
+
+/// Versions of the Solana and Rust toolchains inside a build container
+#[derive(Clone, Debug, Serialize)]
+pub struct Toolchain {
+    pub solana_version: String,
+    pub rustc_version: String,
+}
+
+/// What a program was built with, to reproduce the build or explain a mismatch
+#[derive(Clone, Debug, Serialize)]
+pub struct BuildInfo {
+    /// The build image pinned to its digest, unless the build ran without docker
+    pub image: Option<String>,
+    pub toolchain: Option<Toolchain>,
+    /// The build as a single shell command, as printed by `--print-command`
+    pub build_command: Option<String>,
+}
+
+/// Result for one program of a workspace verification
+#[derive(Debug, Serialize)]
+pub struct WorkspaceProgramResult {
+    pub library_name: String,
+    pub program_id: String,
+    pub executable_hash: Option<String>,
+    pub on_chain_hash: Option<String>,
+    pub verified: bool,
+    pub error: Option<String>,
+}
+
+/// Hash of one of the programs hashed by `hash_programs`
+#[derive(Debug, Serialize)]
+pub struct ProgramHashResult {
+    pub program_id: String,
+    pub hash: Option<String>,
+    /// Size of the program without zero padding, the part that is hashed
+    pub size: Option<usize>,
+    pub error: Option<String>,
+}
+
+/// Parses a `LIB_NAME:PROGRAM_ID` pair naming a workspace program and where it is deployed
+pub fn parse_program_spec(spec: &str) -> anyhow::Result<(String, Pubkey)> {
+    let (library_name, program_id) = spec
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Invalid program \"{}\". Expected LIB_NAME:PROGRAM_ID", spec))?;
+    let program_id = Pubkey::from_str(program_id)
+        .map_err(|e| anyhow!("Invalid program ID \"{}\": {}", program_id, e))?;
+    Ok((library_name.to_string(), program_id))
+}
+
+/// Parses the URL of a repository to clone. Only URLs of network remotes are accepted, so that
+/// untrusted input can't pass options to git or make it read a local path. That includes
+/// `file://` URLs.
+pub fn parse_repo_url(repo_url: &str) -> anyhow::Result<String> {
+    let invalid = |reason: &str| anyhow!("Invalid repo URL \"{}\": {}", repo_url, reason);
+    if repo_url.starts_with('-') {
+        return Err(invalid("must not start with '-'"));
+    }
+    if let Some(c) = repo_url
+        .chars()
+        .find(|c| c.is_whitespace() || c.is_control() || "`$;|&<>(){}'\"\\".contains(*c))
+    {
+        return Err(invalid(&format!("contains the character {:?}", c)));
+    }
+    match Url::parse(repo_url) {
+        Ok(url) if ["https", "http", "ssh", "git"].contains(&url.scheme()) => {
+            Ok(repo_url.to_string())
+        }
+        Ok(url) => Err(invalid(&format!("unsupported scheme {}", url.scheme()))),
+        // SSH remotes can also be written as `user@host:path`
+        Err(_) => match repo_url.split_once(':') {
+            Some((user_host, path))
+                if user_host.contains('@') && !user_host.contains('/') && !path.is_empty() =>
+            {
+                Ok(repo_url.to_string())
+            }
+            _ => Err(invalid(
+                "expected an HTTPS URL or an SSH URL such as git@host:org/repo",
+            )),
+        },
+    }
+}
+
+/// Parses the path of the directory to build, relative to the root of the repository
+pub fn parse_mount_path(mount_path: &str) -> anyhow::Result<String> {
+    let invalid = |reason: &str| anyhow!("Invalid mount path \"{}\": {}", mount_path, reason);
+    if let Some(c) = mount_path
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && !"-_./".contains(*c))
+    {
+        return Err(invalid(&format!("contains the character {:?}", c)));
+    }
+    let path = Path::new(mount_path);
+    if path.is_absolute() || mount_path.starts_with('/') {
+        return Err(invalid("must be relative to the root of the repository"));
+    }
+    if path.components().any(|c| c == Component::ParentDir) {
+        return Err(invalid("must not contain '..'"));
+    }
+    Ok(mount_path.to_string())
+}
+
+/// Parses a commitment level, rejecting anything the RPC wouldn't understand
+pub fn parse_commitment(commitment: &str) -> anyhow::Result<CommitmentConfig> {
+    CommitmentConfig::from_str(commitment).map_err(|_| {
+        anyhow!(
+            "Invalid commitment level \"{}\". Valid values are: processed, confirmed, finalized",
+            commitment
+        )
+    })
+}
+
+/// Parses a docker image reference such as `ellipsislabs/solana:1.18`, `ghcr.io/org/solana` or
+/// `registry.internal:5000/solana@sha256:<digest>`, so that a typo fails before docker is run
+pub fn parse_image_reference(image: &str) -> anyhow::Result<String> {
+    let invalid = |reason: &str| anyhow!("Invalid image reference \"{}\": {}", image, reason);
+    let (name, digest) = match image.split_once('@') {
+        Some((name, digest)) => (name, Some(digest)),
+        None => (image, None),
+    };
+    if let Some(digest) = digest {
+        let hex = digest
+            .strip_prefix("sha256:")
+            .ok_or_else(|| invalid("the digest must start with sha256:"))?;
+        if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid("the digest must be 64 hex digits"));
+        }
+    }
+    let repository = image_repository(image);
+    if let Some(tag) = name
+        .strip_prefix(repository)
+        .and_then(|tag| tag.strip_prefix(':'))
+    {
+        let valid = tag.len() <= 128
+            && tag
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+            && tag
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c));
+        if !valid {
+            return Err(invalid(
+                "the tag may only contain letters, digits, '_', '.' and '-', and not start with '.' or '-'",
+            ));
+        }
+    }
+    let path = match repository.split_once('/') {
+        Some((host, path)) if image_registry(image) == host => {
+            let (host_name, port) = host.split_once(':').unwrap_or((host, "0"));
+            if host_name.is_empty()
+                || !host_name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || ".-".contains(c))
+                || port.parse::<u16>().is_err()
+            {
+                return Err(invalid(
+                    "the registry must be a host name with an optional port",
+                ));
+            }
+            path
+        }
+        _ => repository,
+    };
+    let valid_component = |component: &str| {
+        component.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+            && component.ends_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+            && component
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_.-".contains(c))
+    };
+    if !path.split('/').all(valid_component) {
+        return Err(invalid(
+            "the repository may only contain lowercase letters, digits, '_', '.' and '-', separated by '/'",
+        ));
+    }
+    Ok(image.to_string())
+}
+
+/// The part of an image reference before its tag or digest, e.g. `registry:5000/solana` for
+/// `registry:5000/solana:1.18`
+fn image_repository(image: &str) -> &str {
+    let name = image.split('@').next().unwrap_or(image);
+    match name.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => repository,
+        _ => name,
+    }
+}
+
+/// The registry an image is pulled from: the host the reference starts with, or Docker Hub
+pub fn image_registry(image: &str) -> &str {
+    match image_repository(image).split_once('/') {
+        Some((host, _)) if host.contains('.') || host.contains(':') || host == "localhost" => host,
+        _ => "docker.io",
+    }
+}
+
+/// Parses the name of a docker volume. Anything with a `/` or `:` would be mounted as a path of
+/// the host instead, so only the characters docker allows in volume names are accepted
+pub fn parse_volume_name(name: &str) -> anyhow::Result<String> {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c));
+    if !valid {
+        return Err(anyhow!(
+            "Invalid volume name \"{}\". Use letters, digits, '_', '.' and '-', starting with a letter or digit",
+            name
+        ));
+    }
+    Ok(name.to_string())
+}
+
+/// Parses an HTTP header given as `Key: Value`, as accepted by `curl -H`
+pub fn parse_rpc_header(header: &str) -> anyhow::Result<(String, String)> {
+    let (key, value) = header
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Invalid header \"{}\". Expected \"Key: Value\"", header))?;
+    let (key, value) = (key.trim(), value.trim());
+    reqwest::header::HeaderName::from_bytes(key.as_bytes())
+        .map_err(|_| anyhow!("Invalid header name \"{}\"", key))?;
+    reqwest::header::HeaderValue::from_str(value)
+        .map_err(|_| anyhow!("Invalid value for header \"{}\"", key))?;
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Parses a hex encoded SHA-256 hash supplied on the command line
+pub fn parse_expected_hash(hash: &str) -> anyhow::Result<String> {
+    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!(
+            "Invalid hash \"{}\". Expected 64 hex characters",
+            hash
+        ));
+    }
+    Ok(hash.to_ascii_lowercase())
+}
+
+/// Fails if a hash was expected and the computed hash doesn't match it
+pub fn check_expected_hash(hash: &str, expected_hash: Option<&str>) -> Result<(), VerifyError> {
+    match expected_hash {
+        Some(expected_hash) if hash != expected_hash => Err(VerifyError::HashMismatch {
+            expected: expected_hash.to_string(),
+            actual: hash.to_string(),
+            reason: "Hash does not match the expected hash".to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Options controlling how on-chain data is read over RPC
+#[derive(Args, Clone, Debug)]
+pub struct RpcOptions {
+    /// Commitment level used when reading on-chain data: processed, confirmed or finalized
+    #[clap(long, default_value = "confirmed", value_parser = parse_commitment)]
+    pub commitment: CommitmentConfig,
+    /// Maximum number of times to retry a failed RPC request
+    #[clap(long, default_value = "5")]
+    pub max_retries: u32,
+    /// Read account data in slices of this many bytes, for programs too large for a single request
+    #[clap(long)]
+    pub chunk_size: Option<usize>,
+    /// Seconds to wait for a response to each RPC request
+    #[clap(long, default_value = "60")]
+    pub rpc_timeout: u64,
+    /// Extra HTTP header sent with each RPC request, e.g. the API key of an RPC provider. Repeat
+    /// for several headers
+    #[clap(
+        long = "rpc-header",
+        alias = "json-rpc-header",
+        value_name = "KEY: VALUE",
+        value_parser = parse_rpc_header
+    )]
+    pub rpc_headers: Vec<(String, String)>,
+    /// Fail if the RPC node hasn't reached this slot, e.g. the slot of a recent upgrade. A node
+    /// that is behind may still serve the program as it was before that upgrade
+    #[clap(long, alias = "since-slot", value_name = "SLOT")]
+    pub min_slot: Option<u64>,
+    /// Verify the program as it was deployed at this slot. Nodes only serve the current version
+    /// of a program, so this fails if the program has been deployed again since
+    #[clap(long, value_name = "SLOT")]
+    pub at_slot: Option<u64>,
+    /// Fail if an account read for its program data holds more than this many bytes, so that a
+    /// faulty or malicious RPC node can't make the program hash an enormous account
+    #[clap(long, value_name = "BYTES", default_value_t = DEFAULT_MAX_ACCOUNT_SIZE)]
+    pub max_account_size: usize,
+}
+
+impl Default for RpcOptions {
+    fn default() -> Self {
+        Self {
+            commitment: CommitmentConfig::confirmed(),
+            max_retries: 5,
+            chunk_size: None,
+            rpc_timeout: 60,
+            rpc_headers: vec![],
+            min_slot: None,
+            at_slot: None,
+            max_account_size: DEFAULT_MAX_ACCOUNT_SIZE,
+        }
+    }
+}
+
+/// Ways to give the ID of the program to verify
+#[derive(Args, Clone, Debug, Default)]
+pub struct ProgramIdOptions {
+    /// The Program ID of the program to verify
+    #[clap(short, long)]
+    pub program_id: Option<Pubkey>,
+    /// Read the program ID from the program's keypair file, such as
+    /// `target/deploy/<lib name>-keypair.json`
+    #[clap(long, value_name = "PATH")]
+    pub program_keypair: Option<String>,
+    /// Read the program ID from the address in an Anchor IDL file
+    #[clap(long, value_name = "PATH")]
+    pub idl: Option<String>,
+}
+
+impl ProgramIdOptions {
+    /// The program ID given by any of the options, or `None` if none was given. Fails if more
+    /// than one was given and they disagree.
+    pub fn resolve(&self) -> anyhow::Result<Option<Pubkey>> {
+        let mut sources = vec![];
+        if let Some(program_id) = self.program_id {
+            sources.push(("--program-id".to_string(), program_id));
+        }
+        if let Some(path) = &self.program_keypair {
+            let keypair = read_keypair_file(path)
+                .map_err(|e| anyhow!("Failed to read program keypair {}: {}", path, e))?;
+            sources.push((format!("the keypair {}", path), keypair.pubkey()));
+        }
+        if let Some(path) = &self.idl {
+            sources.push((format!("the IDL {}", path), read_idl_address(path)?));
+        }
+        if let Some((source, program_id)) = sources.first() {
+            if let Some((other, other_id)) = sources.iter().find(|(_, id)| id != program_id) {
+                return Err(anyhow!(
+                    "The program ID {} from {} disagrees with {} from {}",
+                    program_id,
+                    source,
+                    other_id,
+                    other
+                ));
+            }
+        }
+        Ok(sources.first().map(|(_, program_id)| *program_id))
+    }
+
+    /// Like `resolve`, but fails if no program ID was given
+    pub fn program_id(&self) -> anyhow::Result<Pubkey> {
+        self.resolve()?.ok_or_else(|| {
+            anyhow!("No program ID given. Pass --program-id, --program-keypair or --idl")
+        })
+    }
+}
+
+/// Reads the program address from an Anchor IDL. Newer IDLs have it at the top level, older
+/// ones in their metadata.
+fn read_idl_address(path: &str) -> anyhow::Result<Pubkey> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| anyhow!("Failed to read IDL {}: {}", path, e))?;
+    let idl: serde_json::Value =
+        serde_json::from_str(&contents).map_err(|e| anyhow!("Invalid IDL {}: {}", path, e))?;
+    let address = idl["address"]
+        .as_str()
+        .or_else(|| idl["metadata"]["address"].as_str())
+        .ok_or_else(|| anyhow!("IDL {} has no program address", path))?;
+    Pubkey::from_str(address)
+        .map_err(|e| anyhow!("Invalid program address {} in IDL {}: {}", address, path, e))
+}
+
+/// Options controlling how the repository to verify is cloned
+#[derive(Args, Clone, Debug, Default)]
+pub struct CloneOptions {
+    /// Keep clones in this directory and update them on later runs instead of cloning again
+    #[clap(long)]
+    pub cache_dir: Option<String>,
+    /// With --commit-hash, only fetch that commit instead of the full history
+    #[clap(long = "shallow-clone", default_value = "false")]
+    pub shallow: bool,
+    /// Also check out the git submodules of the repository, recursively
+    #[clap(long, default_value = "false")]
+    pub recurse_submodules: bool,
+    /// Remove an existing clone at the destination before cloning, e.g. one left behind by a
+    /// crashed run or a broken clone in --cache-dir, instead of failing or reusing it
+    #[clap(long, default_value = "false")]
+    pub force: bool,
+}
+
+/// Options controlling how long to wait for a job on the remote verification server
+#[derive(Args, Clone, Debug)]
+pub struct PollOptions {
+    /// Seconds to wait between checks of the status of a remote job
+    #[clap(long, default_value = "10", value_parser = clap::value_parser!(u64).range(1..))]
+    pub poll_interval: u64,
+    /// Stop waiting for a remote job after this many seconds. The job keeps running on the server
+    /// and can be resumed with resume-remote-job
+    #[clap(long, default_value = "18000")]
+    pub job_timeout: u64,
+}
+
+impl Default for PollOptions {
+    fn default() -> Self {
+        Self {
+            poll_interval: 10,
+            job_timeout: 18000,
+        }
+    }
+}
+
+/// Options controlling how a program is built inside docker
+#[derive(Args, Clone, Debug)]
+pub struct BuildOptions {
+    /// Optionally specify a custom base docker image to use for building the program repository.
+    /// Images from private registries, e.g. `ghcr.io/org/solana:1.18`, are pulled with the
+    /// credentials of `docker login`
+    #[clap(short, long, value_parser = parse_image_reference)]
+    pub base_image: Option<String>,
+    /// If the program requires cargo build-bpf (instead of cargo build-sbf), as for an Anchor program, set this flag
+    #[clap(long, default_value = "false")]
+    pub bpf: bool,
+    /// Stop the build and fail if it hasn't finished after this many seconds
+    #[clap(long)]
+    pub build_timeout: Option<u64>,
+    /// Platform of the build container. Builds for other platforms, such as the native one on
+    /// Apple Silicon, may use a different toolchain and produce a different hash
+    #[clap(long, default_value = "linux/amd64")]
+    pub platform: String,
+    /// Build with the toolchain of the current environment instead of in a docker container. The
+    /// build is then only reproducible if this environment has the exact toolchain of the image
+    #[clap(long, default_value = "false")]
+    pub no_docker: bool,
+    /// Run the build container as this user. On Unix it defaults to the current user, so that the
+    /// build output isn't owned by root. The image must allow that user to run the Solana toolchain
+    #[clap(long, value_name = "UID:GID")]
+    pub docker_user: Option<String>,
+    /// Run the build container as root instead of the current user
+    #[clap(long, default_value = "false", conflicts_with = "docker_user")]
+    pub docker_root: bool,
+    /// Build an Anchor workspace with `anchor build`. Unless a base image is given, the
+    /// backpackapp/build image of the anchor-lang version in Cargo.lock is used
+    #[clap(long, default_value = "false", conflicts_with = "bpf")]
+    pub anchor: bool,
+    /// Comma-separated list of cargo features to build the program with, e.g. `mainnet`. The
+    /// on-chain program must have been built with the same features
+    #[clap(long, value_delimiter = ',')]
+    pub features: Vec<String>,
+    /// Keep built executables in this directory and reuse them instead of building again when the
+    /// same commit is built with the same image and arguments
+    #[clap(long, value_name = "DIR")]
+    pub artifact_cache: Option<String>,
+    /// Mount this directory as the cargo registry of the build container, so that crates
+    /// downloaded by one build are reused by the next
+    #[clap(long, value_name = "DIR")]
+    pub cargo_cache: Option<String>,
+    /// Shell command to run before the build, from the root of the mounted directory and in the
+    /// same container, e.g. to generate code that the program needs to build
+    #[clap(long, value_name = "COMMAND")]
+    pub pre_build: Option<String>,
+    /// Directory for cargo to build in, instead of the `target` directory of the program, so the
+    /// build doesn't overwrite your own build output. The executables are read from its `deploy`
+    /// directory
+    #[clap(long, value_name = "DIR")]
+    pub target_dir: Option<String>,
+    /// Keep the build output in this docker volume, which is created if it doesn't exist, so that
+    /// later builds only recompile what changed. Not for CI, since an earlier build can leave
+    /// stale output behind
+    #[clap(
+        long,
+        value_name = "NAME",
+        value_parser = parse_volume_name,
+        conflicts_with_all = ["target_dir", "no_docker"]
+    )]
+    pub target_volume: Option<String>,
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        Self {
+            base_image: None,
+            bpf: false,
+            build_timeout: None,
+            platform: "linux/amd64".to_string(),
+            no_docker: false,
+            docker_user: None,
+            docker_root: false,
+            anchor: false,
+            features: vec![],
+            artifact_cache: None,
+            cargo_cache: None,
+            pre_build: None,
+            target_dir: None,
+            target_volume: None,
+        }
+    }
+}
+
+/// Only the scheme, host and port of a URL, as RPC providers put API keys in its path or query
+fn redact_url(url: &str) -> String {
+    Url::parse(url).map_or_else(
+        |_| "<invalid URL>".to_string(),
+        |url| url.origin().ascii_serialization(),
+    )
+}
+
+pub fn get_client(url: Option<String>, rpc: &RpcOptions) -> RpcClient {
+    let config = match CONFIG_FILE.as_ref() {
+        Some(config_file) => Config::load(config_file).unwrap_or_else(|_| {
+            warn!("Failed to load config file: {}", config_file);
+            Config::default()
+        }),
+        None => Config::default(),
+    };
+    let url = &get_network(&url.unwrap_or(config.json_rpc_url)).to_string();
+    debug!("Using RPC URL {}", redact_url(url));
+    if !rpc.rpc_headers.is_empty() {
+        return RpcClient::new_sender(
+            HeaderSender::new(
+                url.clone(),
+                &rpc.rpc_headers,
+                Duration::from_secs(rpc.rpc_timeout),
+            ),
+            RpcClientConfig::with_commitment(rpc.commitment),
+        );
+    }
+    RpcClient::new_with_timeout_and_commitment(
+        url,
+        Duration::from_secs(rpc.rpc_timeout),
+        rpc.commitment,
+    )
+}
+
+/// Runs an RPC request, retrying transient failures with exponential backoff and jitter
+fn retry_rpc<T>(
+    key: &Pubkey,
+    retries: u32,
+    mut request: impl FnMut() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let mut attempt = 0;
+    loop {
+        match request() {
+            Ok(value) => return Ok(value),
+            // The node answered; e.g. the account does not exist, so retrying will not help
+            Err(e) if is_user_error(&e) || e.is::<VerifyError>() => return Err(e),
+            Err(e) if attempt < retries => {
+                let backoff = 500 * 2u64.pow(attempt);
+                let jitter = rand::thread_rng().gen_range(0..=backoff / 2);
+                let delay = Duration::from_millis(backoff + jitter);
+                attempt += 1;
+                warn!(
+                    "Failed to fetch account {}: {}. Retrying in {:?} ({}/{})",
+                    key, e, delay, attempt, retries
+                );
+                std::thread::sleep(delay);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn is_user_error(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<ClientError>()
+        .is_some_and(|e| matches!(e.kind(), ClientErrorKind::RpcError(RpcError::ForUser(_))))
+}
+
+/// Fetches an account, retrying transient RPC failures with exponential backoff and jitter
+pub fn get_account_with_retry(
+    client: &RpcClient,
+    key: &Pubkey,
+    retries: u32,
+) -> anyhow::Result<Account> {
+    retry_rpc(key, retries, || Ok(client.get_account(key)?)).map_err(|e| {
+        if is_account_not_found(&e) {
+            VerifyError::AccountNotFound(*key).into()
+        } else {
+            e
+        }
+    })
+}
+
+/// Fetches an account like `get_account_with_retry`, but with at most one byte more data than
+/// `max_size`. That is enough for `check_account_size` to fail on, without ever downloading an
+/// oversized account as a whole.
+fn get_account_up_to(
+    client: &RpcClient,
+    key: &Pubkey,
+    retries: u32,
+    max_size: usize,
+) -> anyhow::Result<Account> {
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        data_slice: Some(UiDataSliceConfig {
+            offset: 0,
+            length: max_size.saturating_add(1),
+        }),
+        commitment: Some(client.commitment()),
+        min_context_slot: None,
+    };
+    Ok(retry_rpc(key, retries, || {
+        Ok(client.get_account_with_config(key, config.clone())?)
+    })?
+    .value
+    .ok_or(VerifyError::AccountNotFound(*key))?)
+}
+
+fn is_account_not_found(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<ClientError>().is_some_and(|e| {
+        matches!(e.kind(), ClientErrorKind::RpcError(RpcError::ForUser(message)) if message.starts_with("AccountNotFound"))
+    })
+}
+
+/// A source of accounts to read program data from. The hash functions take this instead of an
+/// `RpcClient`, so they can also be run against accounts that don't come from a node.
+pub trait AccountFetcher {
+    /// Fetches an account, failing with `VerifyError::AccountNotFound` if it doesn't exist
+    fn get_account(&self, key: &Pubkey) -> anyhow::Result<Account>;
+
+    /// Fetches only the data of an account
+    fn get_account_data(&self, key: &Pubkey) -> anyhow::Result<Vec<u8>> {
+        Ok(self.get_account(key)?.data)
+    }
+
+    /// Fetches up to `length` bytes of the data of an account starting at `offset`, as of
+    /// `min_context_slot` or later. Returns the slice along with the slot it was read at.
+    fn get_account_data_slice(
+        &self,
+        key: &Pubkey,
+        offset: usize,
+        length: usize,
+        min_context_slot: Option<u64>,
+    ) -> anyhow::Result<(Vec<u8>, u64)>;
+}
+
+/// Fetches every account with a single request, without retries
+impl AccountFetcher for RpcClient {
+    fn get_account(&self, key: &Pubkey) -> anyhow::Result<Account> {
+        get_account_with_retry(self, key, 0)
+    }
+
+    fn get_account_data_slice(
+        &self,
+        key: &Pubkey,
+        offset: usize,
+        length: usize,
+        min_context_slot: Option<u64>,
+    ) -> anyhow::Result<(Vec<u8>, u64)> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            data_slice: Some(UiDataSliceConfig { offset, length }),
+            commitment: Some(self.commitment()),
+            min_context_slot,
+        };
+        let response = self.get_account_with_config(key, config)?;
+        let account = response.value.ok_or(VerifyError::AccountNotFound(*key))?;
+        Ok((account.data, response.context.slot))
+    }
+}
+
+/// Fetches accounts from a node with the retries and chunking of the `RpcOptions`
+pub struct RpcFetcher<'a> {
+    client: &'a RpcClient,
+    rpc: &'a RpcOptions,
+}
+
+impl<'a> RpcFetcher<'a> {
+    pub fn new(client: &'a RpcClient, rpc: &'a RpcOptions) -> Self {
+        Self { client, rpc }
+    }
+}
+
+impl AccountFetcher for RpcFetcher<'_> {
+    fn get_account(&self, key: &Pubkey) -> anyhow::Result<Account> {
+        let max_size = self.rpc.max_account_size;
+        let account = get_account_up_to(self.client, key, self.rpc.max_retries, max_size)?;
+        check_account_size(key, account.data.len(), max_size)?;
+        Ok(account)
+    }
+
+    fn get_account_data(&self, key: &Pubkey) -> anyhow::Result<Vec<u8>> {
+        get_account_data_with_retry(self.client, key, self.rpc)
+    }
+
+    fn get_account_data_slice(
+        &self,
+        key: &Pubkey,
+        offset: usize,
+        length: usize,
+        min_context_slot: Option<u64>,
+    ) -> anyhow::Result<(Vec<u8>, u64)> {
+        self.client
+            .get_account_data_slice(key, offset, length, min_context_slot)
+    }
+}
+
+/// Fetches the data of an account. If `chunk_size` is set, or a single request for the whole
+/// account fails, the data is read in slices instead
+pub fn get_account_data_with_retry(
+    client: &RpcClient,
+    key: &Pubkey,
+    rpc: &RpcOptions,
+) -> anyhow::Result<Vec<u8>> {
+    if let Some(chunk_size) = rpc.chunk_size {
+        return get_account_data_in_chunks(
+            client,
+            key,
+            chunk_size,
+            rpc.max_retries,
+            rpc.max_account_size,
+        );
+    }
+    match get_account_up_to(client, key, rpc.max_retries, rpc.max_account_size) {
+        Ok(account) => {
+            check_account_size(key, account.data.len(), rpc.max_account_size)?;
+            Ok(account.data)
+        }
+        Err(e) if e.is::<VerifyError>() || is_user_error(&e) => Err(e),
+        Err(e) => {
+            warn!(
+                "Failed to fetch account {} in a single request: {}. Reading it in chunks of {} bytes",
+                key, e, DEFAULT_CHUNK_SIZE
+            );
+            get_account_data_in_chunks(
+                client,
+                key,
+                DEFAULT_CHUNK_SIZE,
+                rpc.max_retries,
+                rpc.max_account_size,
+            )
+        }
+    }
+}
+
+/// Fails if an account holds more data than `max_size` bytes
+fn check_account_size(key: &Pubkey, size: usize, max_size: usize) -> anyhow::Result<()> {
+    if size > max_size {
+        return Err(anyhow!(
+            "Account {} holds at least {} bytes, more than the maximum of {} bytes. Pass --max-account-size to read larger accounts",
+            key,
+            size,
+            max_size
+        ));
+    }
+    Ok(())
+}
+
+/// Reads account data in slices of `chunk_size` bytes until a short slice marks the end. Every
+/// slice is read at or after the slot of the first one. Reading stops with an error once more than
+/// `max_size` bytes have been read.
+pub fn get_account_data_in_chunks(
+    fetcher: &impl AccountFetcher,
+    key: &Pubkey,
+    chunk_size: usize,
+    retries: u32,
+    max_size: usize,
+) -> anyhow::Result<Vec<u8>> {
+    if chunk_size == 0 {
+        return Err(anyhow!("Chunk size must be greater than zero"));
+    }
+    let mut data = vec![];
+    let mut min_context_slot = None;
+    loop {
+        let (chunk, slot) = retry_rpc(key, retries, || {
+            fetcher.get_account_data_slice(key, data.len(), chunk_size, min_context_slot)
+        })?;
+        min_context_slot.get_or_insert(slot);
+        let len = chunk.len();
+        data.extend(chunk);
+        debug!("Read {} bytes of account {}", data.len(), key);
+        check_account_size(key, data.len(), max_size)?;
+        if len < chunk_size {
+            return Ok(data);
+        }
+    }
+}
+
+/// Hashes the program data with any trailing zero padding stripped
+pub fn get_binary_hash(program_data: &[u8]) -> String {
+    sha256::digest(trim_zero_padding(program_data))
+}
+
+/// Size of the program data without trailing zero padding, the part that is hashed
+pub fn get_trimmed_size(program_data: &[u8]) -> usize {
+    trim_zero_padding(program_data).len()
+}
+
+/// The program data without the trailing zeros that pad program accounts
+fn trim_zero_padding(program_data: &[u8]) -> &[u8] {
+    let end = program_data
+        .iter()
+        .rposition(|&b| b != 0)
+        .map_or(0, |last_non_zero| last_non_zero + 1);
+    &program_data[..end]
+}
+
+/// Describes how an executable differs from the program it was verified against, e.g. "the
+/// executables differ starting at byte 4096; the on-chain program is 12 bytes longer". Like the
+/// hashes, this ignores trailing zero padding.
+pub fn explain_mismatch(executable: &[u8], on_chain: &[u8]) -> String {
+    let executable = trim_zero_padding(executable);
+    let on_chain = trim_zero_padding(on_chain);
+    let first_difference = first_difference(executable, on_chain);
+    let lengths = match on_chain.len().cmp(&executable.len()) {
+        std::cmp::Ordering::Greater => format!(
+            "the on-chain program is {} bytes longer",
+            on_chain.len() - executable.len()
+        ),
+        std::cmp::Ordering::Less => format!(
+            "the on-chain program is {} bytes shorter",
+            executable.len() - on_chain.len()
+        ),
+        std::cmp::Ordering::Equal => format!("both are {} bytes long", on_chain.len()),
+    };
+    format!(
+        "the executables differ starting at byte {}; {}",
+        first_difference, lengths
+    )
+}
+
+/// Offset of the first byte that differs between two executables, or the length of the shorter
+/// one if it is a prefix of the other
+fn first_difference(a: &[u8], b: &[u8]) -> usize {
+    a.iter()
+        .zip(b)
+        .position(|(a, b)| a != b)
+        .unwrap_or_else(|| a.len().min(b.len()))
+}
+
+/// Incrementally computes the same hash as `get_binary_hash`. Zero bytes are held back until a
+/// non-zero byte follows them, so trailing zero padding never reaches the hasher.
+#[derive(Default)]
+pub struct TrimmedHasher {
+    hasher: Sha256,
+    pending_zeros: usize,
+    hashed_len: usize,
+}
+
+impl TrimmedHasher {
+    const ZEROS: [u8; 4096] = [0; 4096];
+
+    pub fn update(&mut self, data: &[u8]) {
+        match data.iter().rposition(|&b| b != 0) {
+            Some(last_non_zero) => {
+                self.hashed_len += self.pending_zeros + last_non_zero + 1;
+                while self.pending_zeros > 0 {
+                    let n = self.pending_zeros.min(Self::ZEROS.len());
+                    self.hasher.update(&Self::ZEROS[..n]);
+                    self.pending_zeros -= n;
+                }
+                self.hasher.update(&data[..=last_non_zero]);
+                self.pending_zeros = data.len() - last_non_zero - 1;
+            }
+            None => self.pending_zeros += data.len(),
+        }
+    }
+
+    /// Number of bytes hashed so far, which is the size without zero padding once all data has
+    /// been passed to `update`
+    pub fn trimmed_len(&self) -> usize {
+        self.hashed_len
+    }
+
+    pub fn finalize(self) -> String {
+        hex::encode(self.hasher.finalize())
+    }
+}
+
+/// Zeroes the parts of an ELF that don't affect the loaded program: the build-id note and every
+/// section that isn't loaded into memory, such as `.comment` and the symbol tables
+pub fn normalize_elf(data: &mut [u8]) -> anyhow::Result<()> {
+    let elf = Elf::parse(data).map_err(|e| anyhow!("Failed to parse ELF: {}", e))?;
+    let ranges = elf
+        .section_headers
+        .iter()
+        .filter(|section| section.sh_type != SHT_NOBITS)
+        .filter(|section| {
+            section.sh_flags & u64::from(SHF_ALLOC) == 0
+                || elf.shdr_strtab.get_at(section.sh_name) == Some(".note.gnu.build-id")
+        })
+        .filter_map(|section| section.file_range())
+        .collect::<Vec<_>>();
+    for range in ranges {
+        let range = range.start.min(data.len())..range.end.min(data.len());
+        data[range].fill(0);
+    }
+    Ok(())
+}
+
+/// Hashes the program data after normalizing it with `normalize_elf`. These hashes are only
+/// comparable with other normalized hashes, never with the ones from `get_binary_hash`.
+pub fn get_normalized_binary_hash(program_data: &[u8]) -> anyhow::Result<String> {
+    let mut program_data = program_data.to_vec();
+    normalize_elf(&mut program_data)?;
+    Ok(get_binary_hash(&program_data))
+}
+
+/// ELF machine type of programs built for SBFv2 and later, which goblin doesn't know yet
+const EM_SBF: u16 = 263;
+
+/// Checks that a built executable is an ELF for the SBF (or eBPF) machine, so that a broken build,
+/// e.g. one that produced a library for the host, isn't reported as a hash mismatch
+pub fn check_sbf_executable(path: &Path) -> Result<(), VerifyError> {
+    let invalid = |reason: String| VerifyError::InvalidExecutable {
+        path: path.display().to_string(),
+        reason,
+    };
+    let data = std::fs::read(path)
+        .map_err(|e| anyhow!("Failed to read executable {}: {}", path.display(), e))?;
+    let elf = Elf::parse(&data).map_err(|e| invalid(format!("it is not an ELF file ({})", e)))?;
+    match elf.header.e_machine {
+        EM_BPF | EM_SBF => Ok(()),
+        machine => Err(invalid(format!(
+            "it was built for {} instead of SBF. Check that it was built with `cargo build-sbf`",
+            machine_to_str(machine)
+        ))),
+    }
+}
+
+/// Hashes an executable file like `get_binary_hash`, without reading it into memory at once.
+/// Errors name the file that couldn't be read.
+pub fn get_file_hash(filepath: &str) -> anyhow::Result<String> {
+    get_file_hash_and_size(filepath).map(|(hash, _)| hash)
+}
+
+/// Hashes an executable file like `get_file_hash`, and also returns its size without trailing zero
+/// padding, the part of the file that was hashed
+pub fn get_file_hash_and_size(filepath: &str) -> anyhow::Result<(String, usize)> {
+    let read_error = |e: std::io::Error| anyhow!("Failed to read executable {}: {}", filepath, e);
+    if Path::new(filepath).is_dir() {
+        return Err(anyhow!(
+            "Failed to read executable {}: it is a directory",
+            filepath
+        ));
+    }
+    let mut f = std::fs::File::open(filepath).map_err(read_error)?;
+    let mut hasher = TrimmedHasher::default();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        match f.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => hasher.update(&buffer[..n]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(read_error(e)),
+        }
+    }
+    let size = hasher.trimmed_len();
+    Ok((hasher.finalize(), size))
+}
+
+pub fn get_buffer_hash(
+    url: Option<String>,
+    buffer_address: Pubkey,
+    rpc: &RpcOptions,
+) -> anyhow::Result<String> {
+    let program_data = get_buffer_data(url, buffer_address, rpc)?;
+    Ok(get_binary_hash(&program_data))
+}
+
+/// Fetches the program data held by a buffer account, without the buffer metadata
+pub fn get_buffer_data(
+    url: Option<String>,
+    buffer_address: Pubkey,
+    rpc: &RpcOptions,
+) -> anyhow::Result<Vec<u8>> {
+    check_no_at_slot(rpc, "buffer")?;
+    let client = get_client(url, rpc);
+    let program_data = buffer_program_data(&RpcFetcher::new(&client, rpc), buffer_address)?;
+    check_min_slot(&client, rpc)?;
+    Ok(program_data)
+}
+
+/// Reads the program data held by a buffer account, without the buffer metadata
+pub fn buffer_program_data(
+    fetcher: &impl AccountFetcher,
+    buffer_address: Pubkey,
+) -> anyhow::Result<Vec<u8>> {
+    strip_account_metadata(
+        fetcher.get_account_data(&buffer_address)?,
+        UpgradeableLoaderState::size_of_buffer_metadata(),
+        &buffer_address,
+        "upgradeable buffer",
+    )
+}
+
+pub fn get_programdata_hash(
+    url: Option<String>,
+    programdata_address: Pubkey,
+    rpc: &RpcOptions,
+) -> anyhow::Result<String> {
+    let program_data = get_programdata_data(url, programdata_address, rpc)?;
+    Ok(get_binary_hash(&program_data))
+}
+
+/// Fetches the program data held by an upgradeable programdata account, without its metadata.
+/// Unlike `get_program_data`, the programdata address is used as is instead of being derived.
+pub fn get_programdata_data(
+    url: Option<String>,
+    programdata_address: Pubkey,
+    rpc: &RpcOptions,
+) -> anyhow::Result<Vec<u8>> {
+    check_no_at_slot(rpc, "programdata account")?;
+    let client = get_client(url, rpc);
+    let account_data = get_account_data_with_retry(&client, &programdata_address, rpc)?;
+    check_min_slot(&client, rpc)?;
+    strip_account_metadata(
+        account_data,
+        ProgramLoader::Upgradeable.code_offset(),
+        &programdata_address,
+        "upgradeable programdata",
+    )
+}
+
+/// Removes the `offset` bytes of metadata that precede the program data in an account. Every
+/// kind of account holding program data is read through here, so they are all validated alike.
+fn strip_account_metadata(
+    mut account_data: Vec<u8>,
+    offset: usize,
+    account_key: &Pubkey,
+    account_kind: &str,
+) -> anyhow::Result<Vec<u8>> {
+    if account_data.len() < offset {
+        return Err(anyhow!(
+            "account {} is not a valid {} account",
+            account_key,
+            account_kind
+        ));
+    }
+    account_data.drain(..offset);
+    debug!(
+        "Account {} holds {} bytes of program data",
+        account_key,
+        account_data.len()
+    );
+    Ok(account_data)
+}
+
+/// The loaders that can own a program, each of which stores the program code differently
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgramLoader {
+    /// The code lives in a separate programdata account derived from the program ID
+    Upgradeable,
+    /// The code lives in the program account itself, after the state header
+    V4,
+    /// `bpf_loader` and `bpf_loader_deprecated` hold the bare ELF in the program account
+    NonUpgradeable,
+}
+
+impl ProgramLoader {
+    /// Detects the loader from the owner of a program account
+    pub fn from_owner(owner: &Pubkey) -> Option<Self> {
+        if *owner == bpf_loader_upgradeable::id() {
+            Some(ProgramLoader::Upgradeable)
+        } else if *owner == loader_v4::id() {
+            Some(ProgramLoader::V4)
+        } else if *owner == bpf_loader::id() || *owner == bpf_loader_deprecated::id() {
+            Some(ProgramLoader::NonUpgradeable)
+        } else {
+            None
+        }
+    }
+
+    /// Offset of the program code in the account that holds it
+    pub fn code_offset(&self) -> usize {
+        match self {
+            ProgramLoader::Upgradeable => UpgradeableLoaderState::size_of_programdata_metadata(),
+            ProgramLoader::V4 => loader_v4::PROGRAM_DATA_OFFSET,
+            ProgramLoader::NonUpgradeable => 0,
+        }
+    }
+}
+
+impl std::fmt::Display for ProgramLoader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgramLoader::Upgradeable => write!(f, "upgradeable loader"),
+            ProgramLoader::V4 => write!(f, "loader v4"),
+            ProgramLoader::NonUpgradeable => write!(f, "non-upgradeable loader"),
+        }
+    }
+}
+
+/// Address of the programdata account of an upgradeable program
+pub fn get_programdata_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0
+}
+
+pub fn get_program_hash(
+    url: Option<String>,
+    program_id: Pubkey,
+    rpc: &RpcOptions,
+) -> anyhow::Result<String> {
+    let program_data = get_program_data(url, program_id, rpc)?;
+    Ok(get_binary_hash(&program_data))
+}
+
+/// Fetches the deployed program data of a program, without the loader's metadata
+pub fn get_program_data(
+    url: Option<String>,
+    program_id: Pubkey,
+    rpc: &RpcOptions,
+) -> anyhow::Result<Vec<u8>> {
+    let client = get_client(url, rpc);
+    let (_, program_data) = on_chain_program_data(&RpcFetcher::new(&client, rpc), program_id)?;
+    check_min_slot(&client, rpc)?;
+    check_at_slot(&client, program_id, rpc)?;
+    Ok(program_data)
+}
+
+/// Fetches and hashes several deployed programs concurrently, with at most `jobs` programs being
+/// fetched at a time. The results are in the order of `program_ids`.
+pub fn get_program_hashes(
+    url: Option<String>,
+    program_ids: &[Pubkey],
+    rpc: &RpcOptions,
+    jobs: usize,
+) -> anyhow::Result<Vec<ProgramHashResult>> {
+    let client = get_client(url, rpc);
+    let fetcher = RpcFetcher::new(&client, rpc);
+    let next = AtomicUsize::new(0);
+    let mut hashes = std::thread::scope(|scope| {
+        let workers = (0..jobs.clamp(1, program_ids.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut hashes = vec![];
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        match program_ids.get(index) {
+                            Some(program_id) => hashes.push((
+                                index,
+                                on_chain_program_data(&fetcher, *program_id).and_then(
+                                    |(_, program_data)| {
+                                        check_at_slot(&client, *program_id, rpc)?;
+                                        Ok((
+                                            get_binary_hash(&program_data),
+                                            get_trimmed_size(&program_data),
+                                        ))
+                                    },
+                                ),
+                            )),
+                            None => return hashes,
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("hashing thread panicked"))
+            .collect::<Vec<_>>()
+    });
+    check_min_slot(&client, rpc)?;
+    hashes.sort_by_key(|(index, _)| *index);
+    Ok(hashes
+        .into_iter()
+        .map(|(index, hash)| ProgramHashResult {
+            program_id: program_ids[index].to_string(),
+            error: hash.as_ref().err().map(|e| e.to_string()),
+            size: hash.as_ref().ok().map(|(_, size)| *size),
+            hash: hash.ok().map(|(hash, _)| hash),
+        })
+        .collect())
+}
+
+/// Prints the hashes of several deployed programs as a table, failing if any couldn't be hashed.
+/// With `show_size`, the table also lists the size of each program without zero padding.
+pub fn hash_programs(
+    url: Option<String>,
+    program_ids: &[Pubkey],
+    rpc: &RpcOptions,
+    jobs: usize,
+    show_size: bool,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    let results = get_program_hashes(url, program_ids, rpc, jobs)?;
+    match output {
+        OutputFormat::Json => println!("{}", serde_json::to_string(&results)?),
+        OutputFormat::Text if show_size => {
+            println!("{:<44} {:<64} Size", "Program ID", "Hash");
+            for result in &results {
+                match (&result.hash, result.size, &result.error) {
+                    (Some(hash), Some(size), _) => {
+                        println!("{:<44} {} {}", result.program_id, hash, size)
+                    }
+                    (_, _, error) => println!(
+                        "{:<44} error {} {}",
+                        result.program_id,
+                        FAILED,
+                        error.as_deref().unwrap_or_default()
+                    ),
+                }
+            }
+        }
+        OutputFormat::Text => {
+            println!("{:<44} Hash", "Program ID");
+            for result in &results {
+                match (&result.hash, &result.error) {
+                    (Some(hash), _) => println!("{:<44} {}", result.program_id, hash),
+                    (None, error) => println!(
+                        "{:<44} error {} {}",
+                        result.program_id,
+                        FAILED,
+                        error.as_deref().unwrap_or_default()
+                    ),
+                }
+            }
+        }
+    }
+
+    let failed = results
+        .iter()
+        .filter(|result| result.error.is_some())
+        .count();
+    if failed > 0 {
+        return Err(anyhow!(
+            "{} of {} programs could not be hashed",
+            failed,
+            results.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Writes a hash to `path` for `--output-hash-file`, without a trailing newline so that it can be
+/// compared with the file of another run as is
+pub fn write_hash_file(path: &str, hash: &str) -> anyhow::Result<()> {
+    std::fs::write(path, hash).map_err(|e| anyhow!("Failed to write the hash to {}: {}", path, e))
+}
+
+/// Fails if the node is at a slot below `--min-slot`, as the data it served may be outdated
+pub fn check_min_slot(client: &RpcClient, rpc: &RpcOptions) -> anyhow::Result<()> {
+    let min_slot = match rpc.min_slot {
+        Some(min_slot) => min_slot,
+        None => return Ok(()),
+    };
+    let slot = client
+        .get_slot()
+        .map_err(|e| anyhow!("Failed to get the current slot of the RPC node: {}", e))?;
+    debug!("RPC node is at slot {}", slot);
+    if slot < min_slot {
+        return Err(anyhow!(
+            "RPC node {} is at slot {}, below the minimum slot {}. The node may be behind and serve an outdated program, try another RPC URL",
+            client.url(),
+            slot,
+            min_slot
+        ));
+    }
+    Ok(())
+}
+
+/// Fails unless the program that was just read is the version that was deployed at `--at-slot`.
+/// Nodes only serve the current state of an account, but a program that hasn't been deployed
+/// since that slot is still the same as it was then. Call this after reading the program, so a
+/// deployment in between is caught.
+pub fn check_at_slot(
+    client: &RpcClient,
+    program_id: Pubkey,
+    rpc: &RpcOptions,
+) -> anyhow::Result<()> {
+    let at_slot = match rpc.at_slot {
+        Some(at_slot) => at_slot,
+        None => return Ok(()),
+    };
+    // The slot the node answered at, rather than its current slot, is the one the program was read at
+    let slot = retry_rpc(&program_id, rpc.max_retries, || {
+        Ok(client.get_account_with_commitment(&program_id, client.commitment())?)
+    })?
+    .context
+    .slot;
+    if slot < at_slot {
+        return Err(anyhow!(
+            "RPC node {} is at slot {}, which is before slot {}",
+            client.url(),
+            slot,
+            at_slot
+        ));
+    }
+    let deployment = get_program_deployment(client, program_id, rpc)?;
+    if let Some(deployment) = deployment.as_ref().filter(|d| d.slot > at_slot) {
+        return Err(anyhow!(
+            "Program {} was deployed again at slot {}, after slot {}. Nodes only serve the current version of a program, so the version at slot {} can't be read",
+            program_id,
+            deployment.slot,
+            at_slot,
+            at_slot
+        ));
+    }
+    let deployed = match deployment {
+        Some(deployment) => format!("was last deployed at slot {}", deployment.slot),
+        None => "can't be upgraded".to_string(),
+    };
+    print_status(format!(
+        "Read program {} at slot {}. It {}, so it is the version at slot {}",
+        program_id, slot, deployed, at_slot
+    ));
+    Ok(())
+}
+
+/// `--at-slot` only applies to programs, whose deployment slot tells which version they are
+fn check_no_at_slot(rpc: &RpcOptions, account_kind: &str) -> anyhow::Result<()> {
+    match rpc.at_slot {
+        Some(_) => Err(anyhow!(
+            "--at-slot can't be used with a {}, only with a program",
+            account_kind
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Hashes the code of a deployed program, whichever loader it was deployed with
+pub fn on_chain_program_hash(
+    fetcher: &impl AccountFetcher,
+    program_id: Pubkey,
+) -> anyhow::Result<String> {
+    let (_, program_data) = on_chain_program_data(fetcher, program_id)?;
+    Ok(get_binary_hash(&program_data))
+}
+
+/// Fetches the code of a deployed program without the loader's metadata, along with the loader
+/// detected from the owner of the program account
+pub fn on_chain_program_data(
+    fetcher: &impl AccountFetcher,
+    program_id: Pubkey,
+) -> anyhow::Result<(ProgramLoader, Vec<u8>)> {
+    let program_account = fetcher.get_account(&program_id)?;
+    let loader = ProgramLoader::from_owner(&program_account.owner).ok_or_else(|| {
+        anyhow!(
+            "Program {} is owned by {}, which is not a supported loader. Supported loaders are {}, {}, {} and {}",
+            program_id,
+            program_account.owner,
+            bpf_loader_upgradeable::id(),
+            loader_v4::id(),
+            bpf_loader::id(),
+            bpf_loader_deprecated::id()
+        )
+    })?;
+    debug!("Program {} is owned by the {}", program_id, loader);
+    let (account_data, account_key, account_kind) = match loader {
+        ProgramLoader::Upgradeable => {
+            let programdata_address = get_programdata_address(&program_id);
+            // Closing an upgradeable program deletes its programdata account
+            let account_data = fetcher
+                .get_account_data(&programdata_address)
+                .map_err(|e| match e.downcast_ref::<VerifyError>() {
+                    Some(VerifyError::AccountNotFound(_)) => {
+                        VerifyError::ProgramClosed(program_id).into()
+                    }
+                    _ => e,
+                })?;
+            (account_data, programdata_address, "upgradeable programdata")
+        }
+        ProgramLoader::V4 => (program_account.data, program_id, "loader v4 program"),
+        ProgramLoader::NonUpgradeable => (program_account.data, program_id, "program"),
+    };
+    let program_data = strip_account_metadata(
+        account_data,
+        loader.code_offset(),
+        &account_key,
+        account_kind,
+    )?;
+    // The hash of no data would only show up as a confusing mismatch
+    if program_data.iter().all(|&b| b == 0) {
+        return Err(VerifyError::ProgramClosed(program_id).into());
+    }
+    Ok((loader, program_data))
+}
+
+/// Who can upgrade a program and the slot at which it was last deployed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProgramDeployment {
+    /// `None` if the program has been made immutable
+    pub upgrade_authority: Option<Pubkey>,
+    pub slot: u64,
+}
+
+/// Reads the upgrade authority and deployment slot from the header of an upgradeable or loader v4
+/// program. Programs of the non-upgradeable loaders have neither, so `None` is returned for them.
+pub fn get_program_deployment(
+    client: &RpcClient,
+    program_id: Pubkey,
+    rpc: &RpcOptions,
+) -> anyhow::Result<Option<ProgramDeployment>> {
+    let program_account = get_account_with_retry(client, &program_id, rpc.max_retries)?;
+    match ProgramLoader::from_owner(&program_account.owner) {
+        Some(ProgramLoader::Upgradeable) => {}
+        Some(ProgramLoader::V4) => {
+            let header = program_account
+                .data
+                .get(..loader_v4::PROGRAM_DATA_OFFSET)
+                .ok_or_else(|| {
+                    anyhow!("account {} is not a valid loader v4 program", program_id)
+                })?;
+            let slot = u64::from_le_bytes(header[..8].try_into()?);
+            let authority = Pubkey::new_from_array(header[8..40].try_into()?);
+            let status = u64::from_le_bytes(header[40..48].try_into()?);
+            return Ok(Some(ProgramDeployment {
+                upgrade_authority: (status != loader_v4::STATUS_FINALIZED).then_some(authority),
+                slot,
+            }));
+        }
+        Some(ProgramLoader::NonUpgradeable) | None => return Ok(None),
+    }
+
+    // Only the header is needed, so don't download the whole program
+    let programdata_address = get_programdata_address(&program_id);
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        data_slice: Some(UiDataSliceConfig {
+            offset: 0,
+            length: UpgradeableLoaderState::size_of_programdata_metadata(),
+        }),
+        commitment: Some(client.commitment()),
+        min_context_slot: None,
+    };
+    let header = retry_rpc(&programdata_address, rpc.max_retries, || {
+        Ok(client.get_account_with_config(&programdata_address, config.clone())?)
+    })?
+    .value
+    .ok_or(VerifyError::AccountNotFound(programdata_address))?
+    .data;
+    match bincode::deserialize(&header) {
+        Ok(UpgradeableLoaderState::ProgramData {
+            slot,
+            upgrade_authority_address,
+        }) => Ok(Some(ProgramDeployment {
+            upgrade_authority: upgrade_authority_address,
+            slot,
+        })),
+        _ => Err(anyhow!(
+            "account {} is not a valid upgradeable programdata account",
+            programdata_address
+        )),
+    }
+}
+
+/// Fetches the deployment of the program to print alongside the verification result. Failing to
+/// read it only matters if the upgrade authority has to be checked.
+fn fetch_program_deployment(
+    url: Option<String>,
+    program_id: Pubkey,
+    rpc: &RpcOptions,
+    check_authority: Option<Pubkey>,
+) -> anyhow::Result<Option<ProgramDeployment>> {
+    let client = get_client(url, rpc);
+    match get_program_deployment(&client, program_id, rpc) {
+        Ok(deployment) => Ok(deployment),
+        Err(e) if check_authority.is_none() => {
+            debug!("Failed to read the deployment of {}: {}", program_id, e);
+            Ok(None)
+        }
+        Err(e) => Err(e.context("Failed to read the upgrade authority")),
+    }
+}
+
+fn print_program_deployment(upgrade_authority: Option<impl std::fmt::Display>, slot: u64) {
+    match upgrade_authority {
+        Some(authority) => println!("Upgrade authority: {}", authority),
+        None => println!("Upgrade authority: none, the program is immutable"),
+    }
+    println!("Last deployed at slot: {}", slot);
+}
+
+/// The verdict of a verification whose hashes differ, the same for every way of verifying
+fn print_hash_mismatch() {
+    println!("Program hashes do not match {}", FAILED);
+}
+
+/// Fails unless the program can be upgraded by `expected`
+fn check_upgrade_authority(
+    program_id: Pubkey,
+    deployment: Option<&ProgramDeployment>,
+    expected: Option<Pubkey>,
+) -> Result<(), VerifyError> {
+    let expected = match expected {
+        Some(expected) => expected,
+        None => return Ok(()),
+    };
+    match deployment {
+        Some(deployment) if deployment.upgrade_authority == Some(expected) => Ok(()),
+        Some(deployment) => Err(VerifyError::AuthorityMismatch {
+            expected,
+            actual: deployment.upgrade_authority,
+        }),
+        None => Err(anyhow!(
+            "Program {} is not upgradeable, so it has no upgrade authority to check",
+            program_id
+        )
+        .into()),
+    }
+}
+
+pub fn get_genesis_hash(url: Option<String>, rpc: &RpcOptions) -> anyhow::Result<String> {
+    let client = get_client(url, rpc);
+    let genesis_hash = client.get_genesis_hash()?;
+    Ok(genesis_hash.to_string())
+}
+
+
+pub fn get_docker_resource_limits() -> Option<(String, String)> {
+    let memory = std::env::var("SVB_DOCKER_MEMORY_LIMIT").ok();
+    let cpus = std::env::var("SVB_DOCKER_CPU_LIMIT").ok();
+    if memory.is_some() || cpus.is_some() {
+        info!("Using docker resource limits: memory: {:?}, cpus: {:?}", memory, cpus);
+    } else {
+        // Print message to user that they can set these environment variables to limit docker resources
+        info!("No Docker resource limits are set.");
+        info!("You can set the SVB_DOCKER_MEMORY_LIMIT and SVB_DOCKER_CPU_LIMIT environment variables to limit Docker resources.");
+        info!("For example: SVB_DOCKER_MEMORY_LIMIT=2g SVB_DOCKER_CPU_LIMIT=2.");
+    }
+    memory.zip(cpus)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn build(
+    mount_directory: Option<String>,
+    library_name: Option<String>,
+    options: &BuildOptions,
+    require_digest: bool,
+    print_command: bool,
+    cargo_args: Vec<String>,
+    container_id_opt: &mut Option<String>,
+) -> anyhow::Result<Option<BuildInfo>> {
+    let mount_path = mount_directory.unwrap_or(
+        std::env::current_dir()?
+            .as_os_str()
+            .to_str()
+            .ok_or_else(|| anyhow::Error::msg("Invalid path string"))?
+            .to_string(),
+    );
+    info!("Mounting path: {}", mount_path);
+    let library_name = library_name
+        .map(|name| resolve_library_name(Path::new(&mount_path), &name))
+        .transpose()?;
+
+    let lockfile = check_lockfile(&mount_path)?;
+    let deploy_dir = deploy_dir(&mount_path, options);
+
+    if options
+        .pre_build
+        .as_ref()
+        .is_some_and(|command| command.trim().is_empty())
+    {
+        return Err(anyhow!("The --pre-build command is empty"));
+    }
+
+    let bpf_flag = options.bpf;
+    if let Some(base_image) = &options.base_image {
+        if require_digest && !is_pinned_image(base_image) {
+            return Err(anyhow!(
+                "Base image {} is a mutable tag. Pass a pinned digest instead, e.g. {}@sha256:<digest>",
+                base_image,
+                image_repository(base_image)
+            ));
+        }
+    }
+
+    let build_command = if bpf_flag { "build-bpf" } else { "build-sbf" };
+    // Anchor builds the programs of the workspace from its root and passes the arguments after
+    // `--` on to cargo
+    let build_program = if options.anchor {
+        let mut args = vec!["anchor".to_string(), "build".to_string()];
+        if let Some(library_name) = &library_name {
+            args.extend(["-p".to_string(), library_name.clone()]);
+        }
+        args.push("--".to_string());
+        args
+    } else {
+        vec![
+            "cargo".to_string(),
+            build_command.to_string(),
+            "--".to_string(),
+        ]
+    };
+    let build_program = [build_program, feature_args(options)].concat();
+    if !options.anchor && Path::new(&mount_path).join("Anchor.toml").exists() {
+        warn!("Found an Anchor.toml. If this is an Anchor workspace, pass --anchor to build it with `anchor build`");
+    }
+
+    let (major, minor, patch) = get_pkg_version_from_cargo_lock("solana-program", &lockfile)?;
+
+    let mut manifest_path = None;
+
+    let relative_build_path = find_cargo_tomls(Path::new(&mount_path))
+        .into_iter()
+        .find(|p| {
+            p.to_str()
+                .and_then(|p| get_lib_name_from_cargo_toml(p).ok())
+                .is_some_and(|name| name == library_name.clone().unwrap_or_default())
+        })
+        .and_then(|p| {
+            // Paths inside the container always use forward slashes, whatever the host uses
+            let relative = p.parent()?.strip_prefix(&mount_path).ok()?;
+            let relative = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/");
+            manifest_path = Some(if relative.is_empty() {
+                "Cargo.toml".to_string()
+            } else {
+                format!("{}/Cargo.toml", relative)
+            });
+            Some(relative)
+        })
+        .unwrap_or_default();
+    let relative_build_path = if options.anchor {
+        manifest_path = None;
+        String::new()
+    } else {
+        relative_build_path
+    };
+
+    if options.no_docker {
+        return build_without_docker(
+            &mount_path,
+            &relative_build_path,
+            manifest_path.as_deref(),
+            library_name.as_deref(),
+            &build_program,
+            options,
+            print_command,
+            cargo_args,
+        );
+    }
+
+    let anchor_image = if options.anchor && options.base_image.is_none() {
+        let (major, minor, patch) = get_pkg_version_from_cargo_lock("anchor-lang", &lockfile)
+            .map_err(|e| anyhow!("{}. Pass a --base-image that contains the Anchor CLI", e))?;
+        info!("Found Anchor version {}.{}.{}", major, minor, patch);
+        Some(format!("backpackapp/build:v{}.{}.{}", major, minor, patch))
+    } else {
+        None
+    };
+
+    let mut solana_version: Option<String> = None;
+    let  image: String = options.base_image.clone().or(anchor_image).map_or_else(|| {
+        if bpf_flag {
+            // Use this for backwards compatibility with anchor verified builds
+            solana_version = Some("v1.13.5".to_string());
+            Ok("projectserum/build@sha256:75b75eab447ebcca1f471c98583d9b5d82c4be122c470852a022afcf9c98bead".to_string())
+        } else if let Some(digest) = IMAGE_MAP.get(&(major, minor, patch)) {
+                info!("Found docker image for Solana version {}.{}.{}", major, minor, patch);
+                solana_version = Some(format!("v{}.{}.{}", major, minor, patch));
+                Ok(format!("ellipsislabs/solana@{}", digest))
+            } else {
+                warn!("Unable to find docker image for Solana version {}.{}.{}", major, minor, patch);
+                let prev = IMAGE_MAP.range(..(major, minor, patch)).next_back();
+                let next = IMAGE_MAP.range((major, minor, patch)..).next();
+                let (version, digest) = if let Some((version, digest)) = prev {
+                    (version, digest)
+                } else if let Some((version, digest)) = next {
+                    (version, digest)
+                } else {
+                    return Err(anyhow!("Unable to find backup docker image for Solana version {}.{}.{}", major, minor, patch));
+                };
+                warn!("Using backup docker image for Solana version {}.{}.{}", version.0, version.1, version.2);
+                solana_version = Some(format!("v{}.{}.{}", version.0, version.1, version.2));
+                Ok(format!("ellipsislabs/solana@{}", digest))
+            }
+    }, Ok)?;
+
+    // Printing the command must not pull the image or start a container, so the working directory
+    // is only known if the image was pulled before
+    let workdir = if print_command {
+        get_image_workdir(&image).unwrap_or_else(|| {
+            warn!(
+                "Image {} has not been pulled, so its working directory is shown as {}",
+                image, WORKDIR_PLACEHOLDER
+            );
+            WORKDIR_PLACEHOLDER.to_string()
+        })
+    } else {
+        check_docker_installed()?;
+        pull_image(&image, Some(&options.platform))?;
+        std::process::Command::new("docker")
+            .args(["run", "--rm", "--platform", &options.platform])
+            .args([&image, "pwd"])
+            .stderr(Stdio::inherit())
+            .output()
+            .map_err(|e| VerifyError::DockerFailed(format!("Failed to get workdir: {}", e)).into())
+            .and_then(|output| parse_output(output.stdout))?
+    };
+
+    debug!("Workdir: {}", workdir);
+
+    let build_path = format!("{}/{}", workdir, relative_build_path);
+    info!("Building program at {}", build_path);
+
+    let manifest_path_filter = manifest_path
+        .clone()
+        .map(|m| vec!["--manifest-path".to_string(), format!("{}/{}", workdir, m)])
+        .unwrap_or_else(Vec::new);
+
+    if let Some(manifest_path) = &manifest_path {
+        debug!("Building manifest path: {}/{}", workdir, manifest_path);
+    }
+
+    // Solana v1.17 uses Rust 1.73, which defaults to the sparse registry, making
+    // this fetch unnecessary, but requires us to omit the "frozen" argument
+    let prefetch = major == 1 && minor < 17;
+    let locked_args = if prefetch {
+        ["--frozen", "--locked"].as_slice()
+    } else {
+        // To be totally safe, force the build to use the sparse registry
+        [
+            "--config",
+            "registries.crates-io.protocol=\"sparse\"",
+            "--locked",
+        ]
+        .as_slice()
+    };
+    let fetch_args = [
+        "cargo",
+        "--config",
+        "net.git-fetch-with-cli=true",
+        "fetch",
+        "--locked",
+    ];
+
+    // change directory to program/build dir
+    let mount_params = format!("{}:{}", mount_path, workdir);
+
+    // A single `docker run` equivalent to the build below, to reproduce it
+    let reproduce_command = {
+        let mut docker_args = vec![
+            "docker".to_string(),
+            "run".to_string(),
+            "--rm".to_string(),
+            "--platform".to_string(),
+            options.platform.clone(),
+            "-v".to_string(),
+            mount_params.clone(),
+            "-w".to_string(),
+            build_path.clone(),
+        ];
+        docker_args.extend(docker_user_args(options));
+        docker_args.extend(cargo_cache_args(options)?);
+        docker_args.extend(target_dir_args(options)?);
+        if let Some((memory_limit, cpu_limit)) = get_docker_resource_limits() {
+            docker_args.extend(["--memory".to_string(), memory_limit]);
+            docker_args.extend(["--cpus".to_string(), cpu_limit]);
+        }
+        docker_args.push(image.clone());
+
+        let build_args = build_program
+            .iter()
+            .map(String::as_str)
+            .chain(locked_args.iter().copied())
+            .map(|arg| arg.to_string())
+            .chain(manifest_path_filter.iter().cloned())
+            .chain(cargo_args.iter().cloned())
+            .collect::<Vec<_>>();
+        let build_cmd = shell_words::join(build_args);
+        let mut steps = vec![];
+        if prefetch {
+            steps.push(shell_words::join(fetch_args));
+        }
+        if let Some(pre_build) = &options.pre_build {
+            steps.push(format!(
+                "(cd {} && {})",
+                shell_words::quote(&workdir),
+                pre_build
+            ));
+        }
+        let copy_out = options.target_volume.as_ref().map(|_| {
+            let deploy_dir = format!("{}/target/deploy", workdir);
+            format!(
+                "mkdir -p {} && cp -r {}/deploy/. {}",
+                shell_words::quote(&deploy_dir),
+                CONTAINER_TARGET_DIR,
+                shell_words::quote(&deploy_dir)
+            )
+        });
+        if steps.is_empty() && copy_out.is_none() {
+            format!("{} {}", shell_words::join(docker_args), build_cmd)
+        } else {
+            steps.push(build_cmd);
+            steps.extend(copy_out);
+            format!(
+                "{} sh -c {}",
+                shell_words::join(docker_args),
+                shell_words::quote(&steps.join(" && "))
+            )
+        }
+    };
+    if print_command {
+        println!("{}", reproduce_command);
+        return Ok(None);
+    }
+
+    let pinned_image = if is_pinned_image(&image) {
+        image.clone()
+    } else {
+        let pinned_image = get_pinned_image(&image)?;
+        info!("Resolved image {} to {}", image, pinned_image);
+        info!("Pass this digest as the base image to reproduce this build exactly");
+        pinned_image
+    };
+
+    let cache_entry = match &options.artifact_cache {
+        Some(cache_dir) => artifact_cache_entry(
+            cache_dir,
+            &mount_path,
+            &[
+                &pinned_image,
+                &options.platform,
+                &relative_build_path,
+                manifest_path.as_deref().unwrap_or_default(),
+                &shell_words::join(&build_program),
+                &shell_words::join(&cargo_args),
+                options.pre_build.as_deref().unwrap_or_default(),
+            ],
+        ),
+        None => None,
+    };
+    if let Some(cache_entry) = &cache_entry {
+        if restore_artifacts(cache_entry, &deploy_dir)? {
+            print_status(format!(
+                "Reusing the build cached at {}",
+                cache_entry.display()
+            ));
+            if let Some(program_name) = library_name {
+                print_executable_hash(&deploy_dir, &program_name)?;
+            }
+            return Ok(Some(BuildInfo {
+                image: Some(pinned_image),
+                toolchain: None,
+                build_command: Some(reproduce_command),
+            }));
+        }
+    }
+
+
+    let container_id = {
+        let mut cmd = std::process::Command::new("docker");
+            cmd.args(["run", "--rm", "--platform", &options.platform]);
+            cmd.args(["-v", &mount_params, "-dit"]);
+            cmd.args(docker_user_args(options));
+            cmd.args(cargo_cache_args(options)?);
+            cmd.args(target_dir_args(options)?);
+            cmd.stderr(Stdio::inherit());
+
+        if let Some((memory_limit, cpu_limit)) = get_docker_resource_limits() {
+            cmd.arg("--memory").arg(memory_limit).arg("--cpus").arg(cpu_limit);
+        }
+
+        cmd.args([&image, "bash"]);
+        debug!("Running {:?}", cmd);
+        let output = cmd
+            .output()
+            .map_err(|e| VerifyError::DockerFailed(format!("Docker build failed: {}", e)))?;
+
+        parse_output(output.stdout)?
+    };
+
+    // Set the container id so we can kill it later if the process is interrupted
+    container_id_opt.replace(container_id.clone());
+
+    // Knowing the toolchain helps explain a mismatch, but a custom image without the solana CLI can
+    // still build, so this is not fatal
+    let toolchain = get_toolchain(Some(&container_id))
+        .map_err(|e| warn!("Unable to determine the container toolchain: {}", e))
+        .ok();
+    if let Some(toolchain) = &toolchain {
+        print_status(format!("Solana version: {}", toolchain.solana_version));
+        print_status(format!("Rust version: {}", toolchain.rustc_version));
+    }
+    print_status(format!("Build command: {}", reproduce_command));
+
+    let deadline = options
+        .build_timeout
+        .map(|secs| Instant::now() + Duration::from_secs(secs));
+    let started = Instant::now();
+    let spinner = new_spinner("Building program...");
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    let build_result = (|| -> anyhow::Result<()> {
+        if prefetch {
+            // First, we resolve the dependencies and cache them in the Docker container
+            // ARM processors running Linux have a bug where the build fails if the dependencies are not preloaded.
+            // Running the build without the pre-fetch will cause the container to run out of memory.
+            // This is a workaround for that issue.
+            spinner.set_message("Fetching build dependencies...");
+            let mut fetch_cmd = std::process::Command::new("docker");
+            fetch_cmd.args(["exec", &container_id]).args(fetch_args);
+            check_build_status(run_until(&mut fetch_cmd, deadline, Some(&spinner))?)?;
+            info!("Finished fetching build dependencies");
+            spinner.set_message("Building program...");
+        }
+
+        if let Some(pre_build) = &options.pre_build {
+            spinner.set_message("Running the pre-build command...");
+            let mut pre_build_cmd = std::process::Command::new("docker");
+            pre_build_cmd
+                .args(["exec", "-w", &workdir, &container_id])
+                .args(["sh", "-c", pre_build]);
+            check_build_status(run_until(&mut pre_build_cmd, deadline, Some(&spinner))?)?;
+            info!("Finished running the pre-build command");
+            spinner.set_message("Building program...");
+        }
+
+        let mut build_cmd = std::process::Command::new("docker");
+        build_cmd
+            .args(["exec", "-w", &build_path, &container_id])
+            .args(&build_program)
+            .args(locked_args)
+            .args(manifest_path_filter)
+            .args(cargo_args);
+        check_build_status(run_until(&mut build_cmd, deadline, Some(&spinner))?)?;
+
+        // The executables are only in the volume, so copy them to where they are read from
+        if options.target_volume.is_some() {
+            std::fs::create_dir_all(&deploy_dir)?;
+            let output = std::process::Command::new("docker")
+                .arg("cp")
+                .arg(format!(
+                    "{}:{}/deploy/.",
+                    container_id, CONTAINER_TARGET_DIR
+                ))
+                .arg(&deploy_dir)
+                .output()
+                .map_err(|e| {
+                    VerifyError::DockerFailed(format!("Failed to copy the executables: {}", e))
+                })?;
+            if !output.status.success() {
+                return Err(VerifyError::DockerFailed(format!(
+                    "Failed to copy the executables: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    })();
+    if build_result.is_ok() {
+        spinner.finish_with_message(format!(
+            "Build completed (Done in {})",
+            HumanDuration(started.elapsed())
+        ));
+    } else {
+        spinner.finish_and_clear();
+    }
+
+    // Always stop the container, since it is only removed once it stops
+    let kill_result = std::process::Command::new("docker")
+        .args(["kill", &container_id])
+        .output();
+    build_result?;
+    kill_result?;
+
+    info!("Finished building program");
+    info!("Program Solana version: v{}.{}.{}", major, minor, patch);
+
+    if let Some(solana_version) = solana_version {
+        info!("Docker image Solana version: {}", solana_version);
+    }
+
+    // The build itself succeeded, so failing to cache it only costs a rebuild next time
+    if let Some(cache_entry) = &cache_entry {
+        match store_artifacts(&deploy_dir, cache_entry) {
+            Ok(()) => info!("Cached the build at {}", cache_entry.display()),
+            Err(e) => warn!("Failed to cache the build: {}", e),
+        }
+    }
+
+    if let Some(program_name) = library_name {
+        print_executable_hash(&deploy_dir, &program_name)?;
+    }
+    Ok(Some(BuildInfo {
+        image: Some(pinned_image),
+        toolchain,
+        build_command: Some(reproduce_command),
+    }))
+}
+
+/// Path of the Cargo.lock in `mount_path`. The build is `--locked`, so without a lockfile cargo
+/// would only fail deep into the build, and an untracked one can't be reproduced from the commit.
+fn check_lockfile(mount_path: &str) -> anyhow::Result<String> {
+    let lockfile = format!("{}/Cargo.lock", mount_path);
+    if !Path::new(&lockfile).exists() {
+        return Err(anyhow!(
+            "No Cargo.lock found at {}. Verifiable builds require a committed Cargo.lock, so that \
+             every build uses exactly the same dependencies. Run `cargo generate-lockfile` and \
+             commit the Cargo.lock",
+            lockfile
+        ));
+    }
+    if git_head(mount_path).is_ok() {
+        let tracked = std::process::Command::new("git")
+            .args(["-C", mount_path])
+            .args(["ls-files", "--error-unmatch", "Cargo.lock"])
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if !tracked {
+            warn!("Cargo.lock is not committed. Verifiable builds require a committed Cargo.lock, or others can't reproduce this build from the repository");
+        }
+    }
+    Ok(lockfile)
+}
+
+/// Directory of the `--artifact-cache` for a build of the commit checked out in `mount_path` with
+/// the given image and arguments. Builds of anything but a clean checkout aren't cached, since
+/// the commit doesn't describe their source.
+fn artifact_cache_entry(
+    cache_dir: &str,
+    mount_path: &str,
+    build_inputs: &[&str],
+) -> Option<PathBuf> {
+    let commit = match git_head(mount_path) {
+        Ok(commit) => commit,
+        Err(e) => {
+            warn!(
+                "Not caching the build, {} is not a git checkout: {}",
+                mount_path, e
+            );
+            return None;
+        }
+    };
+    let modified = std::process::Command::new("git")
+        .args(["-C", mount_path])
+        .args(["status", "--porcelain", "--untracked-files=no"])
+        .output()
+        .map(|output| !output.status.success() || !output.stdout.is_empty())
+        .unwrap_or(true);
+    if modified {
+        warn!(
+            "Not caching the build, {} has uncommitted changes",
+            mount_path
+        );
+        return None;
+    }
+    let key = sha256::digest(
+        std::iter::once(commit.as_str())
+            .chain(build_inputs.iter().copied())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    );
+    debug!("Artifact cache key: {}", key);
+    Some(Path::new(cache_dir).join(key))
+}
+
+/// Copies the executables of a cached build into the deploy directory. Returns false if the build
+/// isn't cached.
+fn restore_artifacts(cache_entry: &Path, deploy_dir: &Path) -> anyhow::Result<bool> {
+    if !cache_entry.is_dir() {
+        return Ok(false);
+    }
+    std::fs::create_dir_all(deploy_dir)?;
+    for entry in std::fs::read_dir(cache_entry)? {
+        let path = entry?.path();
+        if let Some(file_name) = path.file_name() {
+            std::fs::copy(&path, deploy_dir.join(file_name))?;
+        }
+    }
+    Ok(true)
+}
+
+/// Stores the executables in the deploy directory under the cache entry. They are copied to a
+/// temporary directory first, so a cache entry is never seen half written.
+fn store_artifacts(deploy_dir: &Path, cache_entry: &Path) -> anyhow::Result<()> {
+    let staging = cache_entry.with_extension(format!("tmp-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&staging)?;
+    let result = (|| -> anyhow::Result<()> {
+        for entry in std::fs::read_dir(deploy_dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "so") {
+                if let Some(file_name) = path.file_name() {
+                    std::fs::copy(&path, staging.join(file_name))?;
+                }
+            }
+        }
+        std::fs::rename(&staging, cache_entry)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        remove_temp_dir(&staging.to_string_lossy())?;
+    }
+    result
+}
+
+/// Cargo arguments to enable the `--features` of the build
+fn feature_args(options: &BuildOptions) -> Vec<String> {
+    if options.features.is_empty() {
+        return vec![];
+    }
+    vec!["--features".to_string(), options.features.join(",")]
+}
+
+/// User to run the build container as: the `--docker-user`, or else the current user on Unix.
+/// `None` runs it as root, like when the current user is root anyway.
+fn container_user(options: &BuildOptions) -> Option<String> {
+    if options.docker_user.is_some() || options.docker_root {
+        return options.docker_user.clone();
+    }
+    #[cfg(unix)]
+    {
+        // SAFETY: getuid and getgid can't fail and have no preconditions
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        if uid != 0 {
+            return Some(format!("{}:{}", uid, gid));
+        }
+    }
+    None
+}
+
+/// Arguments to run the build container as the [`container_user`]. That user has no home
+/// directory in the image, so cargo is pointed at a writable one.
+fn docker_user_args(options: &BuildOptions) -> Vec<String> {
+    match container_user(options) {
+        Some(user) => vec![
+            "--user".to_string(),
+            user,
+            "-e".to_string(),
+            "HOME=/tmp".to_string(),
+            "-e".to_string(),
+            "CARGO_HOME=/tmp/cargo".to_string(),
+        ],
+        None => vec![],
+    }
+}
+
+/// Directory that the executables of a build of `mount_path` are written to
+pub fn deploy_dir(mount_path: &str, options: &BuildOptions) -> PathBuf {
+    match &options.target_dir {
+        Some(target_dir) => Path::new(target_dir).join("deploy"),
+        None => Path::new(mount_path).join("target").join("deploy"),
+    }
+}
+
+/// Arguments to mount the `--target-dir` or `--target-volume` into the build container and have
+/// cargo build there
+fn target_dir_args(options: &BuildOptions) -> anyhow::Result<Vec<String>> {
+    let source = match (&options.target_dir, &options.target_volume) {
+        (Some(target_dir), _) => {
+            std::fs::create_dir_all(target_dir)
+                .map_err(|e| anyhow!("Failed to create target directory {}: {}", target_dir, e))?;
+            std::fs::canonicalize(target_dir)?
+                .to_string_lossy()
+                .to_string()
+        }
+        (None, Some(target_volume)) => target_volume.clone(),
+        (None, None) => return Ok(vec![]),
+    };
+    Ok(vec![
+        "-v".to_string(),
+        format!("{}:{}", source, CONTAINER_TARGET_DIR),
+        "-e".to_string(),
+        format!("CARGO_TARGET_DIR={}", CONTAINER_TARGET_DIR),
+    ])
+}
+
+/// Arguments to mount the `--cargo-cache` as the registry of cargo in the build container. Crates
+/// are still checked against the checksums in Cargo.lock, so a stale or tampered cache fails the
+/// build instead of changing it.
+fn cargo_cache_args(options: &BuildOptions) -> anyhow::Result<Vec<String>> {
+    let cache_dir = match &options.cargo_cache {
+        Some(cache_dir) => cache_dir,
+        None => return Ok(vec![]),
+    };
+    // Docker only mounts absolute paths, so the directory has to exist to be resolved
+    std::fs::create_dir_all(cache_dir)
+        .map_err(|e| anyhow!("Failed to create cargo cache {}: {}", cache_dir, e))?;
+    let cache_dir = std::fs::canonicalize(cache_dir)?;
+    let cargo_home = if container_user(options).is_some() {
+        "/tmp/cargo"
+    } else {
+        "/root/.cargo"
+    };
+    Ok(vec![
+        "-v".to_string(),
+        format!("{}:{}/registry", cache_dir.display(), cargo_home),
+    ])
+}
+
+/// Builds the program with the toolchain of the current environment instead of in a container
+#[allow(clippy::too_many_arguments)]
+fn build_without_docker(
+    mount_path: &str,
+    relative_build_path: &str,
+    manifest_path: Option<&str>,
+    library_name: Option<&str>,
+    build_program: &[String],
+    options: &BuildOptions,
+    print_command: bool,
+    cargo_args: Vec<String>,
+) -> anyhow::Result<Option<BuildInfo>> {
+    let build_path = Path::new(mount_path).join(relative_build_path);
+    let build_args = build_program
+        .iter()
+        .map(String::as_str)
+        .chain(["--frozen", "--locked"])
+        .map(|arg| arg.to_string())
+        .chain(manifest_path.into_iter().flat_map(|m| {
+            let manifest_path = Path::new(mount_path).join(m);
+            [
+                "--manifest-path".to_string(),
+                manifest_path.to_string_lossy().to_string(),
+            ]
+        }))
+        .chain(cargo_args)
+        .collect::<Vec<_>>();
+    // Cargo runs in the build path, so a relative target directory has to be resolved first
+    let target_dir = options
+        .target_dir
+        .as_ref()
+        .map(|target_dir| {
+            std::fs::create_dir_all(target_dir)
+                .and_then(|_| std::fs::canonicalize(target_dir))
+                .map_err(|e| anyhow!("Failed to create target directory {}: {}", target_dir, e))
+        })
+        .transpose()?;
+
+    // The build below as a single shell command, to reproduce it
+    let reproduce_command = {
+        let pre_build = options
+            .pre_build
+            .as_ref()
+            .map(|pre_build| {
+                format!(
+                    "(cd {} && {}) && ",
+                    shell_words::quote(mount_path),
+                    pre_build
+                )
+            })
+            .unwrap_or_default();
+        let target_dir = target_dir
+            .as_ref()
+            .map(|target_dir| {
+                format!(
+                    "CARGO_TARGET_DIR={} ",
+                    shell_words::quote(&target_dir.to_string_lossy())
+                )
+            })
+            .unwrap_or_default();
+        format!(
+            "{}cd {} && {}{}",
+            pre_build,
+            shell_words::quote(&build_path.to_string_lossy()),
+            target_dir,
+            shell_words::join(&build_args)
+        )
+    };
+    if print_command {
+        println!("{}", reproduce_command);
+        return Ok(None);
+    }
+
+    warn!("Building without docker. The build is only reproducible if this environment has exactly the toolchain the program was originally built with");
+    let toolchain = get_toolchain(None)
+        .map_err(|e| warn!("Unable to determine the toolchain: {}", e))
+        .ok();
+    if let Some(toolchain) = &toolchain {
+        print_status(format!("Solana version: {}", toolchain.solana_version));
+        print_status(format!("Rust version: {}", toolchain.rustc_version));
+    }
+    print_status(format!("Build command: {}", reproduce_command));
+
+    let deadline = options
+        .build_timeout
+        .map(|secs| Instant::now() + Duration::from_secs(secs));
+    let started = Instant::now();
+    let spinner = new_spinner("Building program...");
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    let build_result = (|| -> anyhow::Result<()> {
+        if let Some(pre_build) = &options.pre_build {
+            spinner.set_message("Running the pre-build command...");
+            let mut pre_build_cmd = std::process::Command::new("sh");
+            pre_build_cmd
+                .current_dir(mount_path)
+                .args(["-c", pre_build]);
+            check_build_status(run_until(&mut pre_build_cmd, deadline, Some(&spinner))?)?;
+            info!("Finished running the pre-build command");
+            spinner.set_message("Building program...");
+        }
+        let mut build_cmd = std::process::Command::new(&build_args[0]);
+        build_cmd.current_dir(&build_path).args(&build_args[1..]);
+        if let Some(target_dir) = &target_dir {
+            build_cmd.env("CARGO_TARGET_DIR", target_dir);
+        }
+        check_build_status(run_until(&mut build_cmd, deadline, Some(&spinner))?)
+    })();
+    if build_result.is_ok() {
+        spinner.finish_with_message(format!(
+            "Build completed (Done in {})",
+            HumanDuration(started.elapsed())
+        ));
+    } else {
+        spinner.finish_and_clear();
+    }
+    build_result?;
+    info!("Finished building program");
+
+    if let Some(program_name) = library_name {
+        print_executable_hash(&deploy_dir(mount_path, options), program_name)?;
+    }
+    Ok(Some(BuildInfo {
+        image: None,
+        toolchain,
+        build_command: Some(reproduce_command),
+    }))
+}
+
+fn print_executable_hash(deploy_dir: &Path, library_name: &str) -> anyhow::Result<()> {
+    let executable_path = find_executable_in(deploy_dir, Some(library_name))?;
+    check_sbf_executable(&executable_path)?;
+    let executable_hash = get_file_hash(&executable_path.to_string_lossy())?;
+    // The hash is the result of a build, but only a line of progress while verifying with JSON output
+    if is_json_output() {
+        print_status(executable_hash);
+    } else {
+        println!("{}", executable_hash);
+    }
+    Ok(())
+}
+
+/// Asks a running build container, or the current environment without one, which versions of
+/// the Solana CLI and rustc it contains
+fn get_toolchain(container_id: Option<&str>) -> anyhow::Result<Toolchain> {
+    let get_version = |program: &str| {
+        let output = match container_id {
+            Some(container_id) => std::process::Command::new("docker")
+                .args(["exec", container_id, program, "--version"])
+                .output()
+                .map_err(|e| {
+                    VerifyError::DockerFailed(format!("Failed to run {}: {}", program, e))
+                })?,
+            None => std::process::Command::new(program)
+                .arg("--version")
+                .output()
+                .map_err(|e| anyhow!("Failed to run {}: {}", program, e))?,
+        };
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{} --version exited with {}",
+                program,
+                output.status
+            ));
+        }
+        parse_output(output.stdout)
+    };
+    Ok(Toolchain {
+        solana_version: get_version("solana")?,
+        rustc_version: get_version("rustc")?,
+    })
+}
+
+/// Runs a command, streaming its output while keeping the last lines of it for error reporting.
+/// The command is stopped and fails once the deadline has passed.
+fn run_until(
+    cmd: &mut std::process::Command,
+    deadline: Option<Instant>,
+    spinner: Option<&ProgressBar>,
+) -> anyhow::Result<(ExitStatus, Vec<String>)> {
+    debug!("Running {:?}", cmd);
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let tail = Arc::new(Mutex::new(VecDeque::new()));
+    let readers = [
+        forward_lines(
+            child.stdout.take().unwrap(),
+            tail.clone(),
+            false,
+            spinner.cloned(),
+        ),
+        forward_lines(
+            child.stderr.take().unwrap(),
+            tail.clone(),
+            true,
+            spinner.cloned(),
+        ),
+    ];
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            child.kill()?;
+            child.wait()?;
+            return Err(VerifyError::DockerFailed("Build timed out".to_string()).into());
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    };
+    for reader in readers {
+        let _ = reader.join();
+    }
+
+    let tail = tail.lock().unwrap().drain(..).collect();
+    Ok((status, tail))
+}
+
+/// Echoes each line of the output and remembers the last `BUILD_LOG_TAIL_LINES` of them. While a
+/// spinner is shown, lines are only printed above it with --verbose.
+fn forward_lines(
+    output: impl Read + Send + 'static,
+    tail: Arc<Mutex<VecDeque<String>>>,
+    to_stderr: bool,
+    spinner: Option<ProgressBar>,
+) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        for line in BufReader::new(output).lines().map_while(Result::ok) {
+            match &spinner {
+                Some(spinner) if !spinner.is_hidden() => {
+                    if log_enabled!(Level::Info) {
+                        spinner.println(&line);
+                    }
+                }
+                _ if is_quiet() => {}
+                _ if to_stderr || is_json_output() => eprintln!("{}", line),
+                _ => println!("{}", line),
+            }
+            let mut tail = tail.lock().unwrap();
+            if tail.len() == BUILD_LOG_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+    })
+}
+
+/// Creates a spinner that shows the elapsed time next to `message`
+pub fn new_spinner(message: impl Into<Cow<'static, str>>) -> ProgressBar {
+    let spinner_style =
+        ProgressStyle::with_template("[{elapsed_precise}] {prefix:.bold.dim} {spinner} {wide_msg}")
+            .unwrap()
+            .tick_chars(if is_plain_output() {
+                "|/-\\ "
+            } else {
+                "⠁⠂⠄⡀⢀⠠⠐⠈ "
+            });
+
+    let pb = if is_quiet() {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new_spinner()
+    };
+    pb.set_style(spinner_style);
+    pb.set_message(message);
+    pb
+}
+
+/// Fails with the end of the build log if the command didn't exit successfully
+fn check_build_status((status, log): (ExitStatus, Vec<String>)) -> anyhow::Result<()> {
+    if !status.success() {
+        return Err(VerifyError::BuildFailed {
+            status: status.to_string(),
+            log: log.join("\n"),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Embeds an access token into an HTTPS repository URL so that private repositories can be cloned
+pub fn get_authenticated_repo_url(repo_url: &str, token: &str) -> anyhow::Result<String> {
+    match repo_url.strip_prefix("https://") {
+        Some(rest) => Ok(format!("https://x-access-token:{}@{}", token, rest)),
+        None => Err(anyhow!(
+            "A git token can only be used with an HTTPS repository URL. SSH URLs (git@...) use your ssh-agent instead"
+        )),
+    }
+}
+
+/// Replaces any occurrence of the token with a placeholder so it never ends up in logs
+pub fn redact_token(text: &str, token: Option<&str>) -> String {
+    match token {
+        Some(token) if !token.is_empty() => text.replace(token, "<redacted>"),
+        _ => text.to_string(),
+    }
+}
+
+/// Returns true if the image reference is pinned to an immutable digest rather than a tag
+pub fn is_pinned_image(image: &str) -> bool {
+    image.contains("@sha256:")
+}
+
+/// Fails with instructions to install docker if it isn't on the PATH, rather than with the error
+/// of the first docker command that can't be started
+fn check_docker_installed() -> Result<(), VerifyError> {
+    let status = std::process::Command::new("docker")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    match status {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err(VerifyError::DockerFailed(format!(
+                "docker is not installed, or not on the PATH. Builds run in a docker container so that they can be reproduced. {}",
+                docker_install_hint()
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn docker_install_hint() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "Install Docker Desktop from https://docs.docker.com/desktop/install/mac-install/ and start it"
+    } else if cfg!(target_os = "windows") {
+        "Install Docker Desktop from https://docs.docker.com/desktop/install/windows-install/ and start it"
+    } else {
+        "Install Docker Engine as described at https://docs.docker.com/engine/install/, e.g. with `sudo apt-get install docker.io` on Debian or Ubuntu"
+    }
+}
+
+/// Pulls the image unless it is already present, so that the pull shows up as its own step
+/// instead of being mixed into the build output of `docker run`. Private images are pulled with
+/// the credentials that `docker login` stored for their registry
+pub fn pull_image(image: &str, platform: Option<&str>) -> Result<(), VerifyError> {
+    let present = std::process::Command::new("docker")
+        .args(["image", "inspect", image])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if present {
+        debug!("Image {} is already present", image);
+        return Ok(());
+    }
+
+    let started = Instant::now();
+    let spinner = new_spinner(format!("Pulling image {}...", image));
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    let mut cmd = std::process::Command::new("docker");
+    cmd.arg("pull");
+    if let Some(platform) = platform {
+        cmd.args(["--platform", platform]);
+    }
+    let output = cmd
+        .arg(image)
+        .output()
+        .map_err(|e| VerifyError::DockerFailed(format!("Failed to pull {}: {}", image, e)))?;
+    if !output.status.success() {
+        spinner.finish_and_clear();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let registry = image_registry(image);
+        if is_registry_auth_error(&stderr) {
+            return Err(VerifyError::DockerFailed(format!(
+                "Failed to pull {}: the registry {} denied access ({}). If the image is private, run `docker login {}` with an account that can read it",
+                image,
+                registry,
+                stderr.trim(),
+                registry
+            )));
+        }
+        return Err(VerifyError::DockerFailed(format!(
+            "Failed to pull {}: {}",
+            image,
+            stderr.trim()
+        )));
+    }
+    let digest = get_pinned_image(image).unwrap_or_else(|_| image.to_string());
+    spinner.finish_with_message(format!(
+        "Pulled image {} (Done in {})",
+        digest,
+        HumanDuration(started.elapsed())
+    ));
+    Ok(())
+}
+
+/// Whether `docker pull` failed because the registry wants credentials it wasn't given
+fn is_registry_auth_error(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    [
+        "unauthorized",
+        "denied",
+        "authentication required",
+        "no basic auth credentials",
+    ]
+    .iter()
+    .any(|message| stderr.contains(message))
+}
+
+/// Working directory of an image that was already pulled, without starting a container. `None` if
+/// the image isn't available locally.
+fn get_image_workdir(image: &str) -> Option<String> {
+    let output = std::process::Command::new("docker")
+        .args([
+            "image",
+            "inspect",
+            "--format",
+            "{{.Config.WorkingDir}}",
+            image,
+        ])
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let workdir = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    // Containers start in the root directory of images that don't set one
+    Some(if workdir.is_empty() {
+        "/".to_string()
+    } else {
+        workdir
+    })
+}
+
+/// Resolves a locally available image to its pinned `repo@sha256:...` reference
+pub fn get_pinned_image(image: &str) -> anyhow::Result<String> {
+    std::process::Command::new("docker")
+        .args([
+            "image",
+            "inspect",
+            "--format",
+            "{{index .RepoDigests 0}}",
+            image,
+        ])
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| anyhow!("Failed to inspect image {}: {}", image, e))
+        .and_then(|output| {
+            parse_output(output.stdout)
+                .map_err(|_| anyhow!("Failed to resolve digest for image {}", image))
+        })
+}
+
+/// A known good build image and the toolchain it was built with
+#[derive(Debug, Serialize)]
+pub struct ImageInfo {
+    pub solana_version: String,
+    pub image: String,
+    pub rustc_version: Option<String>,
+}
+
+/// Lists the build images known to produce verifiable builds, optionally only those for Solana
+/// versions starting with `solana_version` (e.g. `1.17`). With `query_toolchain`, each image is
+/// pulled and run to report the rustc version it contains.
+pub fn list_images(
+    solana_version: Option<&str>,
+    query_toolchain: bool,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    let filter = solana_version
+        .map(|version| {
+            version
+                .trim_start_matches('v')
+                .split('.')
+                .map(|part| part.parse::<u32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| anyhow!("Invalid Solana version \"{}\"", version))
+        })
+        .transpose()?;
+
+    let mut images = vec![];
+    for ((major, minor, patch), digest) in IMAGE_MAP.iter() {
+        let version = [*major, *minor, *patch];
+        if filter
+            .as_ref()
+            .is_some_and(|filter| !version.starts_with(filter))
+        {
+            continue;
+        }
+        let image = format!("ellipsislabs/solana@{}", digest);
+        let rustc_version = if query_toolchain {
+            info!("Querying toolchain of {}", image);
+            Some(
+                std::process::Command::new("docker")
+                    .args(["run", "--rm", &image, "rustc", "--version"])
+                    .stderr(Stdio::inherit())
+                    .output()
+                    .map_err(|e| {
+                        VerifyError::DockerFailed(format!("Failed to run {}: {}", image, e)).into()
+                    })
+                    .and_then(|output| {
+                        parse_output(output.stdout)
+                            .map_err(|_| anyhow!("Failed to get the rustc version of {}", image))
+                    })?,
+            )
+        } else {
+            None
+        };
+        images.push(ImageInfo {
+            solana_version: format!("{}.{}.{}", major, minor, patch),
+            image,
+            rustc_version,
+        });
+    }
+
+    match output {
+        OutputFormat::Json => println!("{}", serde_json::to_string(&images)?),
+        OutputFormat::Text => {
+            println!("{:<16} {:<30} Image", "Solana version", "Rust version");
+            for image in &images {
+                println!(
+                    "{:<16} {:<30} {}",
+                    image.solana_version,
+                    image.rustc_version.as_deref().unwrap_or("-"),
+                    image.image
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs a command and returns the first line it printed, or why it failed
+fn run_check(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("failed to run {}: {}", program, e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(stderr
+            .lines()
+            .chain(stdout.lines())
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("no output")
+            .trim()
+            .to_string());
+    }
+    Ok(stdout.lines().next().unwrap_or_default().trim().to_string())
+}
+
+/// Checks that the environment can build and verify programs: docker is installed and running,
+/// the default build image can be pulled and the RPC endpoint responds. Prints a checklist and
+/// fails if any of the required checks failed.
+pub fn selfcheck(url: Option<String>, rpc: &RpcOptions) -> anyhow::Result<()> {
+    let mut failed = 0;
+    let mut report = |required: bool, name: &str, result: Result<String, String>| match result {
+        Ok(detail) if detail.is_empty() => println!("{} {}", DONE, name),
+        Ok(detail) => println!("{} {} ({})", DONE, name, detail),
+        Err(e) if required => {
+            failed += 1;
+            println!("{} {}: {}", FAILED, name, e);
+        }
+        Err(e) => println!("{} {}: {}", WARNING, name, e),
+    };
+
+    let docker = run_check("docker", &["--version"]);
+    let docker_installed = docker.is_ok();
+    report(true, "Docker is installed", docker);
+    let daemon = if docker_installed {
+        run_check("docker", &["info", "--format", "{{.ServerVersion}}"])
+            .map(|version| format!("server {}", version))
+    } else {
+        Err("skipped, docker is not installed".to_string())
+    };
+    let daemon_running = daemon.is_ok();
+    report(true, "Docker daemon is running", daemon);
+
+    let image = IMAGE_MAP
+        .iter()
+        .next_back()
+        .map(|(_, digest)| format!("ellipsislabs/solana@{}", digest))
+        .ok_or_else(|| anyhow!("No build images are known"))?;
+    let pull = if !daemon_running {
+        Err("skipped, the docker daemon is not running".to_string())
+    } else if run_check("docker", &["image", "inspect", &image]).is_ok() {
+        Ok("already present".to_string())
+    } else {
+        let spinner = new_spinner(format!("Pulling {}...", image));
+        spinner.enable_steady_tick(Duration::from_millis(100));
+        let result = run_check("docker", &["pull", "--quiet", &image]).map(|_| String::new());
+        spinner.finish_and_clear();
+        result
+    };
+    report(
+        true,
+        &format!("Default build image {} can be pulled", image),
+        pull,
+    );
+
+    let client = get_client(url, rpc);
+    let version = client
+        .get_version()
+        .map(|version| format!("solana-core {}", version.solana_core))
+        .map_err(|e| e.to_string());
+    report(
+        true,
+        &format!("RPC endpoint {} responds", client.url()),
+        version,
+    );
+
+    // Only needed to build without docker
+    report(
+        false,
+        "cargo build-sbf is installed, for --no-docker",
+        run_check("cargo", &["build-sbf", "--version"]),
+    );
+
+    if failed > 0 {
+        return Err(anyhow!("{} required check(s) failed", failed));
+    }
+    Ok(())
+}
+
+/// Expands `executable_path` inside the container if it is a glob such as `target/deploy/*.so`.
+/// Exactly one file has to match.
+fn resolve_executable_path(
+    container_id: &str,
+    workdir: &str,
+    executable_path: &str,
+) -> Result<String, VerifyError> {
+    if !executable_path.contains(['*', '?', '[']) {
+        return Ok(executable_path.to_string());
+    }
+    // The pattern is passed as an argument and only expanded by the shell, never evaluated
+    let output = std::process::Command::new("docker")
+        .args(["exec", container_id, "sh", "-c"])
+        .arg(r#"cd "$1" && for f in $2; do [ -f "$f" ] && echo "$f"; done"#)
+        .args(["sh", workdir, executable_path])
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| VerifyError::DockerFailed(format!("Failed to list executables: {}", e)))?;
+    let matches = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    match matches.as_slice() {
+        [path] => {
+            info!("Resolved {} to {}", executable_path, path);
+            Ok(path.clone())
+        }
+        [] => Err(anyhow!("No file in the image matches {}", executable_path).into()),
+        _ => Err(anyhow!(
+            "Multiple files in the image match {}, please pass one of them: {}",
+            executable_path,
+            matches.join(", ")
+        )
+        .into()),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn verify_from_image(
+    executable_path: String,
+    image: String,
+    network: Option<String>,
+    program_id: Pubkey,
+    buffer_address: Option<Pubkey>,
+    current_dir: bool,
+    rpc: &RpcOptions,
+    expected_hash: Option<String>,
+    check_authority: Option<Pubkey>,
+    temp_dir: &mut Option<String>,
+    container_id_opt: &mut Option<String>,
+) -> Result<VerificationResult, VerifyError> {
+    info!(
+        "Verifying image: {:?}, on network {:?} against program ID {}",
+        image, network, program_id
+    );
+    info!("Executable path in container: {:?}", executable_path);
+
+    check_docker_installed()?;
+    pull_image(&image, None)?;
+
+    let workdir = std::process::Command::new("docker")
+        .args(["run", "--rm", &image, "pwd"])
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| VerifyError::DockerFailed(format!("Failed to get workdir: {}", e)))
+        .and_then(|output| Ok(parse_output(output.stdout)?))?;
+
+    debug!("Workdir: {}", workdir);
+
+
+    let container_id = {
+        let mut cmd = std::process::Command::new("docker");
+            cmd.args(["run", "--rm", "-dit"]);
+            cmd.stderr(Stdio::inherit());
+
+        if let Some((memory_limit, cpu_limit)) = get_docker_resource_limits() {
+            cmd.arg("--memory").arg(memory_limit).arg("--cpus").arg(cpu_limit);
+        }
+
+        cmd.args([&image]);
+        debug!("Running {:?}", cmd);
+        let output = cmd
+            .output()
+            .map_err(|e| VerifyError::DockerFailed(format!("Docker run failed: {}", e)))?;
+        parse_output(output.stdout)?
+    };
+
+    container_id_opt.replace(container_id.clone());
+
+    let executable_path = resolve_executable_path(&container_id, &workdir, &executable_path)?;
+
+    let uuid = Uuid::new_v4().to_string();
+
+    // Create a unique temporary directory to copy the executable into, so that concurrent
+    // verifications don't overwrite each other's files
+    let verify_dir = if current_dir {
+        std::env::current_dir()?.join(format!(".{}", uuid))
+    } else {
+        std::env::temp_dir().join("solana-verify").join(uuid)
+    }
+    .to_str()
+    .ok_or_else(|| anyhow::Error::msg("Invalid path string"))?
+    .to_string();
+
+    temp_dir.replace(verify_dir.clone());
+    std::fs::create_dir_all(&verify_dir)
+        .map_err(|e| anyhow!("Failed to create temp dir {}: {}", verify_dir, e))?;
+
+    let program_filepath = Path::new(&verify_dir)
+        .join("program.so")
+        .to_string_lossy()
+        .to_string();
+    std::process::Command::new("docker")
+        .args([
+            "cp",
+            format!("{}:{}/{}", container_id, workdir, executable_path).as_str(),
+            program_filepath.as_str(),
+        ])
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| VerifyError::DockerFailed(format!("Failed to copy executable file: {}", e)))?;
+
+    check_sbf_executable(Path::new(&program_filepath))?;
+    let (executable_hash, executable_size) = get_file_hash_and_size(program_filepath.as_str())?;
+    if let Ok(metadata) = std::fs::metadata(&program_filepath) {
+        debug!("Executable is {} bytes", metadata.len());
+    }
+    // A known hash allows verifying the image offline, without reading the program over RPC
+    let offline = expected_hash.is_some();
+    let program_data = match (&expected_hash, buffer_address) {
+        (Some(_), _) => None,
+        (None, Some(buffer_address)) => {
+            Some(get_buffer_data(network.clone(), buffer_address, rpc)?)
+        }
+        (None, None) => Some(get_program_data(network.clone(), program_id, rpc)?),
+    };
+    let on_chain_size = program_data.as_deref().map(get_trimmed_size);
+    let program_hash = match (expected_hash, program_data) {
+        (Some(expected_hash), _) => expected_hash,
+        (None, program_data) => get_binary_hash(&program_data.unwrap_or_default()),
+    };
+    let deployment = if offline && check_authority.is_none() {
+        None
+    } else {
+        fetch_program_deployment(network, program_id, rpc, check_authority)?
+    };
+
+    // Cleanup docker and rm temp dir
+    std::process::Command::new("docker")
+        .args(["kill", container_id.as_str()])
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| VerifyError::DockerFailed(format!("Docker kill failed: {}", e)))?;
+
+    remove_temp_dir(&verify_dir)
+        .map_err(|e| anyhow::format_err!("Failed to remove temp dir: {}", e.to_string()))?;
+
+    check_upgrade_authority(program_id, deployment.as_ref(), check_authority)?;
+    Ok(VerificationResult {
+        program_id: program_id.to_string(),
+        verified: program_hash == executable_hash,
+        executable_hash,
+        on_chain_hash: program_hash,
+        toolchain: None,
+        build_command: None,
+        executable_size: Some(executable_size),
+        on_chain_size,
+        upgrade_authority: deployment
+            .as_ref()
+            .and_then(|deployment| deployment.upgrade_authority)
+            .map(|authority| authority.to_string()),
+        last_deployed_slot: deployment.as_ref().map(|deployment| deployment.slot),
+    })
+}
+
+/// The result of comparing two executable files with `compare_files`
+#[derive(Debug, Serialize)]
+pub struct FileComparison {
+    pub hash_a: String,
+    pub hash_b: String,
+    pub matches: bool,
+    /// Offset of the first byte that differs, ignoring trailing zero padding like the hashes do.
+    /// Only found when asked for and the files differ
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_difference: Option<usize>,
+}
+
+/// Hashes two executable files to tell whether they are the same program, without any RPC.
+/// With `find_first_difference`, files that differ are also read to find where they do.
+pub fn compare_files(
+    a: &str,
+    b: &str,
+    find_first_difference: bool,
+) -> anyhow::Result<FileComparison> {
+    let hash_a = get_file_hash(a)?;
+    let hash_b = get_file_hash(b)?;
+    let matches = hash_a == hash_b;
+    let first_difference = if find_first_difference && !matches {
+        let read = |path: &str| {
+            std::fs::read(path).map_err(|e| anyhow!("Failed to read executable {}: {}", path, e))
+        };
+        let (a, b) = (read(a)?, read(b)?);
+        Some(self::first_difference(
+            trim_zero_padding(&a),
+            trim_zero_padding(&b),
+        ))
+    } else {
+        None
+    };
+    Ok(FileComparison {
+        hash_a,
+        hash_b,
+        matches,
+        first_difference,
+    })
+}
+
+/// Copies a built executable to `<output_dir>/<program_id>.so`, replacing an earlier copy, so it
+/// can be kept once the clone it was built in is removed
+fn copy_executable(
+    executable_path: &Path,
+    output_dir: &str,
+    program_id: Pubkey,
+) -> anyhow::Result<PathBuf> {
+    std::fs::create_dir_all(output_dir)
+        .map_err(|e| anyhow!("Failed to create output directory {}: {}", output_dir, e))?;
+    let destination = Path::new(output_dir).join(format!("{}.so", program_id));
+    std::fs::copy(executable_path, &destination).map_err(|e| {
+        anyhow!(
+            "Failed to copy the executable to {}: {}",
+            destination.display(),
+            e
+        )
+    })?;
+    Ok(destination)
+}
+
+/// Prints the result of `compare_files` and fails if the files don't match
+pub fn print_file_comparison(
+    comparison: &FileComparison,
+    a: &str,
+    b: &str,
+    output: OutputFormat,
+) -> Result<(), VerifyError> {
+    match output {
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string(comparison).map_err(anyhow::Error::from)?
+        ),
+        OutputFormat::Text => {
+            println!("{}: {}", a, comparison.hash_a);
+            println!("{}: {}", b, comparison.hash_b);
+            if let Some(offset) = comparison.first_difference {
+                println!("The files differ starting at byte {}", offset);
+            }
+            if comparison.matches {
+                println!("Files match {}", DONE);
+            } else {
+                println!("Files do not match {}", FAILED);
+            }
+        }
+    }
+
+    if !comparison.matches {
+        return Err(VerifyError::HashMismatch {
+            expected: comparison.hash_a.clone(),
+            actual: comparison.hash_b.clone(),
+            reason: "Files do not match".to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Prints the result of `verify_from_image` and fails if the hashes don't match.
+/// `against_expected_hash` and `buffer_address` tell whether the executable was compared with an
+/// expected hash or a buffer instead of the on-chain program.
+pub fn print_image_verification(
+    result: &VerificationResult,
+    output: OutputFormat,
+    against_expected_hash: bool,
+    buffer_address: Option<Pubkey>,
+) -> Result<(), VerifyError> {
+    match output {
+        OutputFormat::Json => print_verification_result(result)?,
+        OutputFormat::Text => {
+            println!("Executable hash: {}", result.executable_hash);
+            if against_expected_hash {
+                println!("Expected hash: {}", result.on_chain_hash);
+            } else if let Some(buffer_address) = buffer_address {
+                println!("Buffer hash ({}): {}", buffer_address, result.on_chain_hash);
+            } else {
+                println!("Program hash: {}", result.on_chain_hash);
+            }
+            if let Some(slot) = result.last_deployed_slot {
+                print_program_deployment(result.upgrade_authority.as_ref(), slot);
+            }
+            if result.verified && against_expected_hash {
+                println!("Executable matches the expected hash {}", DONE);
+            } else if result.verified && buffer_address.is_some() {
+                println!("Executable matches the buffer {}", DONE);
+            } else if result.verified {
+                println!("Executable matches on-chain program data {}", DONE);
+            } else {
+                print_hash_mismatch();
+            }
+        }
+    }
+
+    if !result.verified {
+        let compared_with = match buffer_address {
+            Some(_) => "the buffer",
+            None => "the on-chain program",
+        };
+        let sizes = match (result.executable_size, result.on_chain_size) {
+            (Some(executable_size), Some(on_chain_size)) => format!(
+                ", the executable is {} bytes, {} is {} bytes (without zero padding)",
+                executable_size, compared_with, on_chain_size
+            ),
+            (Some(executable_size), None) => format!(
+                ", the executable is {} bytes (without zero padding)",
+                executable_size
+            ),
+            _ => String::new(),
+        };
+        return Err(VerifyError::HashMismatch {
+            expected: result.on_chain_hash.clone(),
+            actual: result.executable_hash.clone(),
+            reason: format!("Program hashes do not match{}", sizes),
+        });
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn verify_from_repo(
+    remote: bool,
+    remote_url: String,
+    relative_mount_path: String,
+    connection_url: Option<String>,
+    repo_url: String,
+    git_token: Option<String>,
+    commit_hash: Option<String>,
+    program_id: Pubkey,
+    buffer_address: Option<Pubkey>,
+    compare_file: Option<String>,
+    library_name_opt: Option<String>,
+    build_options: &BuildOptions,
+    cargo_args: Vec<String>,
+    current_dir: bool,
+    keep_clone: bool,
+    clone_options: &CloneOptions,
+    output: OutputFormat,
+    rpc: &RpcOptions,
+    poll_options: &PollOptions,
+    expected_hash: Option<String>,
+    check_authority: Option<Pubkey>,
+    explain: bool,
+    report_path: Option<String>,
+    signer: Option<&Keypair>,
+    output_hash_file: Option<String>,
+    output_dir: Option<String>,
+    container_id_opt: &mut Option<String>,
+    temp_dir_opt: &mut Option<String>,
+) -> Result<(), VerifyError> {
+    if remote {
+        if buffer_address.is_some() {
+            return Err(anyhow!("Remote verification does not support buffer accounts. Please omit the --remote flag to verify locally.").into());
+        }
+        if expected_hash.is_some() {
+            return Err(anyhow!("Remote verification does not support an expected hash. Please omit the --remote flag to verify locally.").into());
+        }
+        if compare_file.is_some() {
+            return Err(anyhow!("Remote verification does not support comparing against a file. Please omit the --remote flag to verify locally.").into());
+        }
+        if git_token.is_some() {
+            return Err(anyhow!("Remote verification does not support private repositories. Please omit the --remote flag to verify locally.").into());
+        }
+        if report_path.is_some() {
+            return Err(anyhow!("Remote verification does not support writing a report. Please omit the --remote flag to verify locally.").into());
+        }
+        if build_options.anchor {
+            return Err(anyhow!("Remote verification does not support Anchor builds. Please omit the --remote flag to verify locally.").into());
+        }
+        if check_authority.is_some() {
+            return Err(anyhow!("Remote verification does not support checking the upgrade authority. Please omit the --remote flag to verify locally.").into());
+        }
+        if rpc.min_slot.is_some() {
+            return Err(anyhow!("Remote verification does not support a minimum slot. Please omit the --remote flag to verify locally.").into());
+        }
+        if rpc.at_slot.is_some() {
+            return Err(anyhow!("Remote verification does not support verifying at a slot. Please omit the --remote flag to verify locally.").into());
+        }
+        if output_hash_file.is_some() {
+            return Err(anyhow!("Remote verification does not support writing the hash to a file. Please omit the --remote flag to verify locally.").into());
+        }
+        if output_dir.is_some() {
+            return Err(anyhow!("Remote verification does not support copying the executable out. Please omit the --remote flag to verify locally.").into());
+        }
+        if build_options.pre_build.is_some() {
+            return Err(anyhow!("Remote verification does not support a pre-build command. Please omit the --remote flag to verify locally.").into());
+        }
+        if build_options.target_dir.is_some() || build_options.target_volume.is_some() {
+            return Err(anyhow!("Remote verification does not support a target directory. Please omit the --remote flag to verify locally.").into());
+        }
+        if explain {
+            return Err(anyhow!("Remote verification does not support explaining a mismatch. Please omit the --remote flag to verify locally.").into());
+        }
+
+        return Ok(verify_remote(
+            remote_url,
+            connection_url,
+            rpc,
+            repo_url,
+            commit_hash,
+            program_id,
+            library_name_opt,
+            build_options.bpf,
+            relative_mount_path,
+            build_options.base_image.clone(),
+            [feature_args(build_options), cargo_args].concat(),
+            poll_options,
+            output,
+        )
+        .await?);
+    }
+
+    parse_mount_path(&relative_mount_path)?;
+    // Check the dump before spending a build on it
+    if let Some(compare_file) = &compare_file {
+        if !Path::new(compare_file).is_file() {
+            return Err(anyhow!("File to compare against not found: {}", compare_file).into());
+        }
+    }
+    // Likewise the upgrade authority, so a program the caller doesn't control fails within seconds
+    if check_authority.is_some() {
+        let deployment =
+            fetch_program_deployment(connection_url.clone(), program_id, rpc, check_authority)?;
+        check_upgrade_authority(program_id, deployment.as_ref(), check_authority)?;
+    }
+    let (verify_dir, verify_tmp_root_path) = clone_repo(
+        &repo_url,
+        git_token.as_deref(),
+        commit_hash.as_deref(),
+        current_dir,
+        keep_clone,
+        clone_options,
+        temp_dir_opt,
+    )?;
+
+    // Get the absolute build path to the solana program directory to build inside docker
+    let mount_path = PathBuf::from(verify_tmp_root_path.clone()).join(relative_mount_path);
+    debug!("Build path: {:?}", mount_path);
+
+    let library_name = match library_name_opt {
+        Some(p) => resolve_library_name(&mount_path, &p)?,
+        None => {
+            let options = find_cargo_tomls(&mount_path)
+                .iter()
+                .filter_map(|path| path.to_str())
+                .filter_map(|path| get_lib_name_from_cargo_toml(path).ok())
+                .collect::<Vec<_>>();
+            if options.len() != 1 {
+                error!(
+                    "Found multiple possible targets in root directory: {:?}",
+                    options
+                );
+                error!(
+                    "Please explicitly specify the target with the --package-name <name> option",
+                );
+                Err(anyhow::format_err!(
+                    "Failed to find unique Cargo.toml file in root directory"
+                ))?
+            } else {
+                options[0].clone()
+            }
+        }
+    };
+    info!("Verifying program: {}", library_name);
+
+    let result = build_and_verify_repo(
+        mount_path.to_str().unwrap().to_string(),
+        build_options,
+        library_name.clone(),
+        connection_url.clone(),
+        program_id,
+        buffer_address,
+        compare_file.as_deref(),
+        cargo_args,
+        rpc,
+        container_id_opt,
+    );
+
+    // Record the size of the build before the clone is removed, to help explain a mismatch
+    let executable_path = find_executable_in(
+        &deploy_dir(mount_path.to_str().unwrap(), build_options),
+        Some(&library_name),
+    )
+    .ok();
+    let executable_size = executable_path
+        .as_ref()
+        .and_then(|path| get_file_hash_and_size(&path.to_string_lossy()).ok())
+        .map(|(_, size)| size);
+    let copied_executable = output_dir
+        .as_ref()
+        .zip(executable_path.as_ref())
+        .filter(|_| result.is_ok())
+        .map(|(output_dir, path)| copy_executable(path, output_dir, program_id));
+    let executable = executable_path
+        .filter(|_| explain)
+        .and_then(|path| std::fs::read(path).ok());
+
+    // The exact commit that was built, in case a branch or tag was checked out
+    let built_commit = git_head(&verify_tmp_root_path).ok();
+
+    // Cleanup no matter the result, unless the clone should be kept for inspection
+    match verify_dir {
+        Some(verify_dir) if !keep_clone => remove_temp_dir(&verify_dir)?,
+        _ => print_status(format!("Retained cloned repo at: {}", verify_tmp_root_path)),
+    }
+
+    // Compare hashes or return error
+    let (build_hash, program_hash, on_chain_size, build_info) =
+        result.map_err(|e| e.context("Error verifying program"))?;
+    if let Some(copied_executable) = copied_executable {
+        let path = copied_executable?;
+        print_status(format!("Copied the executable to {}", path.display()));
+    }
+    let verified = build_hash == program_hash;
+    if let Some(output_hash_file) = &output_hash_file {
+        write_hash_file(output_hash_file, &build_hash)?;
+    }
+    let toolchain = build_info.as_ref().and_then(|info| info.toolchain.clone());
+    let build_command = build_info
+        .as_ref()
+        .and_then(|info| info.build_command.clone());
+    // A dump file allows verifying offline, so only go to the RPC if the authority has to be checked
+    let deployment = if compare_file.is_some() && check_authority.is_none() {
+        None
+    } else {
+        fetch_program_deployment(connection_url.clone(), program_id, rpc, check_authority)?
+    };
+    if let Some(report_path) = report_path {
+        VerificationReport {
+            repo_url: repo_url.clone(),
+            commit_hash: built_commit.or(commit_hash),
+            program_id: program_id.to_string(),
+            buffer_address: buffer_address.map(|address| address.to_string()),
+            base_image: build_info.and_then(|info| info.image),
+            toolchain: toolchain.clone(),
+            build_command: build_command.clone(),
+            executable_hash: build_hash.clone(),
+            on_chain_hash: program_hash.clone(),
+            verified,
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        }
+        .write(&report_path)?;
+        info!("Wrote verification report to {}", report_path);
+        // Only a successful verification is worth vouching for
+        if let Some(signer) = signer.filter(|_| verified) {
+            let attestation = ReportAttestation::sign(&report_path, signer)?;
+            print_status(format!(
+                "Signed the report as {}: {}",
+                attestation.signer, attestation.signature
+            ));
+        }
+    }
+    match output {
+        OutputFormat::Json => print_verification_result(&VerificationResult {
+            program_id: program_id.to_string(),
+            executable_hash: build_hash.clone(),
+            on_chain_hash: program_hash.clone(),
+            verified,
+            toolchain,
+            build_command,
+            executable_size,
+            on_chain_size: Some(on_chain_size),
+            upgrade_authority: deployment
+                .as_ref()
+                .and_then(|deployment| deployment.upgrade_authority)
+                .map(|authority| authority.to_string()),
+            last_deployed_slot: deployment.as_ref().map(|deployment| deployment.slot),
+        })?,
+        OutputFormat::Text => {
+            if let Some(toolchain) = toolchain.as_ref().filter(|_| !is_quiet()) {
+                println!(
+                    "Built with: {}, {}",
+                    toolchain.solana_version, toolchain.rustc_version
+                );
+            }
+            println!("Executable Program Hash from repo: {}", build_hash);
+            if let Some(compare_file) = &compare_file {
+                println!("File Hash ({}): {}", compare_file, program_hash);
+            } else if let Some(buffer_address) = buffer_address {
+                println!("Buffer Hash ({}): {}", buffer_address, program_hash);
+            } else {
+                println!("On-chain Program Hash: {}", program_hash);
+            }
+            if let Some(deployment) = &deployment {
+                print_program_deployment(deployment.upgrade_authority, deployment.slot);
+            }
+
+            if verified {
+                println!("Program hash matches {}", DONE);
+            } else {
+                print_hash_mismatch();
+            }
+        }
+    }
+
+    if !verified {
+        if let Some(executable) = &executable {
+            match explain_verification_mismatch(
+                executable,
+                connection_url.clone(),
+                program_id,
+                buffer_address,
+                compare_file.as_deref(),
+                rpc,
+            ) {
+                Ok(explanation) => {
+                    return Err(VerifyError::HashMismatch {
+                        expected: program_hash,
+                        actual: build_hash,
+                        reason: format!("Program hashes do not match, {}", explanation),
+                    })
+                }
+                Err(e) => warn!("Unable to explain the mismatch: {}", e),
+            }
+        }
+        let on_chain = match &compare_file {
+            Some(compare_file) => format!(
+                "{} is {} bytes (without zero padding)",
+                compare_file, on_chain_size
+            ),
+            None => {
+                let client = get_client(connection_url, rpc);
+                describe_on_chain_program(&client, program_id, buffer_address, rpc)
+            }
+        };
+        let executable = match executable_size {
+            Some(size) => format!("the executable is {} bytes", size),
+            None => "the executable size is unknown".to_string(),
+        };
+        return Err(VerifyError::HashMismatch {
+            expected: program_hash,
+            actual: build_hash,
+            reason: format!("Program hashes do not match, {}, {}", executable, on_chain),
+        });
+    }
+    check_expected_hash(&build_hash, expected_hash.as_deref())?;
+    check_upgrade_authority(program_id, deployment.as_ref(), check_authority)
+}
+
+/// Reads the program that the executable was verified against and explains how they differ, for
+/// `--explain`. A program read over RPC is saved to a temporary file to be inspected further.
+fn explain_verification_mismatch(
+    executable: &[u8],
+    connection_url: Option<String>,
+    program_id: Pubkey,
+    buffer_address: Option<Pubkey>,
+    compare_file: Option<&str>,
+    rpc: &RpcOptions,
+) -> anyhow::Result<String> {
+    if let Some(compare_file) = compare_file {
+        let on_chain = std::fs::read(compare_file)
+            .map_err(|e| anyhow!("Failed to read {}: {}", compare_file, e))?;
+        return Ok(explain_mismatch(executable, &on_chain));
+    }
+    let (address, on_chain) = match buffer_address {
+        Some(buffer_address) => (
+            buffer_address,
+            get_buffer_data(connection_url, buffer_address, rpc)?,
+        ),
+        None => (
+            program_id,
+            get_program_data(connection_url, program_id, rpc)?,
+        ),
+    };
+    let on_chain = trim_zero_padding(&on_chain);
+    let path = std::env::temp_dir().join(format!("{}.so", address));
+    std::fs::write(&path, on_chain)
+        .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+    Ok(format!(
+        "{}. The on-chain program was saved to {}",
+        explain_mismatch(executable, on_chain),
+        path.display()
+    ))
+}
+
+/// Clones the repository once, builds the whole workspace once, then checks each built program
+/// against its deployed program ID
+#[allow(clippy::too_many_arguments)]
+pub fn verify_workspace(
+    relative_mount_path: String,
+    connection_url: Option<String>,
+    repo_url: String,
+    git_token: Option<String>,
+    commit_hash: Option<String>,
+    programs: Vec<(String, Pubkey)>,
+    build_options: &BuildOptions,
+    cargo_args: Vec<String>,
+    current_dir: bool,
+    keep_clone: bool,
+    clone_options: &CloneOptions,
+    output: OutputFormat,
+    rpc: &RpcOptions,
+    container_id_opt: &mut Option<String>,
+    temp_dir_opt: &mut Option<String>,
+) -> Result<(), VerifyError> {
+    parse_mount_path(&relative_mount_path)?;
+    let (verify_dir, verify_tmp_root_path) = clone_repo(
+        &repo_url,
+        git_token.as_deref(),
+        commit_hash.as_deref(),
+        current_dir,
+        keep_clone,
+        clone_options,
+        temp_dir_opt,
+    )?;
+    let mount_path = PathBuf::from(&verify_tmp_root_path).join(relative_mount_path);
+    let mount_path = mount_path.to_string_lossy().to_string();
+    debug!("Build path: {}", mount_path);
+
+    let build_result = build(
+        Some(mount_path.clone()),
+        None,
+        build_options,
+        false,
+        false,
+        cargo_args,
+        container_id_opt,
+    );
+
+    let deploy_dir = deploy_dir(&mount_path, build_options);
+    let results = build_result.map(|_| {
+        programs
+            .into_iter()
+            .map(|(library_name, program_id)| {
+                info!("Verifying program {} ({})", library_name, program_id);
+                let hashes = find_executable_in(&deploy_dir, Some(&library_name))
+                    .and_then(|path| {
+                        check_sbf_executable(&path)?;
+                        get_file_hash(&path.to_string_lossy())
+                    })
+                    .and_then(|build_hash| {
+                        let program_hash =
+                            get_program_hash(connection_url.clone(), program_id, rpc)?;
+                        Ok((build_hash, program_hash))
+                    });
+                match hashes {
+                    Ok((build_hash, program_hash)) => WorkspaceProgramResult {
+                        library_name,
+                        program_id: program_id.to_string(),
+                        verified: build_hash == program_hash,
+                        executable_hash: Some(build_hash),
+                        on_chain_hash: Some(program_hash),
+                        error: None,
+                    },
+                    Err(e) => WorkspaceProgramResult {
+                        library_name,
+                        program_id: program_id.to_string(),
+                        executable_hash: None,
+                        on_chain_hash: None,
+                        verified: false,
+                        error: Some(e.to_string()),
+                    },
+                }
+            })
+            .collect::<Vec<_>>()
+    });
+
+    // Cleanup no matter the result, unless the clone should be kept for inspection
+    match verify_dir {
+        Some(verify_dir) if !keep_clone => remove_temp_dir(&verify_dir)?,
+        _ => print_status(format!("Retained cloned repo at: {}", verify_tmp_root_path)),
+    }
+
+    let results = results.map_err(|e| e.context("Error building workspace"))?;
+    Ok(print_workspace_results(&results, output)?)
+}
+
+/// Prints the result of each program of a workspace as a table, and fails if any of them wasn't
+/// verified
+fn print_workspace_results(
+    results: &[WorkspaceProgramResult],
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    match output {
+        OutputFormat::Json => println!("{}", serde_json::to_string(results)?),
+        OutputFormat::Text => {
+            println!("{:<32} {:<44} Result", "Library", "Program ID");
+            for result in results {
+                let status = match (&result.error, result.verified) {
+                    (Some(e), _) => format!("error {} {}", FAILED, e),
+                    (None, true) => format!("verified {}", DONE),
+                    (None, false) => format!("mismatch {}", FAILED),
+                };
+                println!(
+                    "{:<32} {:<44} {}",
+                    result.library_name, result.program_id, status
+                );
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|result| !result.verified).count();
+    if failed > 0 {
+        return Err(anyhow!(
+            "{} of {} programs failed verification",
+            failed,
+            results.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Clones the repository into a new temporary directory and checks out `commit_hash`, if given.
+/// Returns the temporary directory and the path of the clone inside it. With a cache directory,
+/// the clone is reused across runs and no temporary directory is returned.
+fn clone_repo(
+    repo_url: &str,
+    git_token: Option<&str>,
+    commit_hash: Option<&str>,
+    current_dir: bool,
+    keep_clone: bool,
+    clone_options: &CloneOptions,
+    temp_dir_opt: &mut Option<String>,
+) -> anyhow::Result<(Option<String>, String)> {
+    parse_repo_url(repo_url)?;
+
+    // Get source code from repo_url
+    let base_name = Path::new(repo_url)
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("Failed to get basename of repo_url: {}", repo_url))?;
+
+    let clone_url = match git_token {
+        Some(token) => get_authenticated_repo_url(repo_url, token)?,
+        None => repo_url.to_string(),
+    };
+
+    if let Some(cache_dir) = &clone_options.cache_dir {
+        let cached_path = Path::new(cache_dir)
+            .join(base_name)
+            .to_string_lossy()
+            .to_string();
+        if clone_options.force && Path::new(&cached_path).exists() {
+            info!("Removing cached repo at: {}", cached_path);
+            remove_temp_dir(&cached_path)?;
+        }
+        let updated = Path::new(&cached_path).exists() && {
+            info!("Updating cached repo at: {}", cached_path);
+            match update_cached_clone(&cached_path, repo_url, &clone_url, git_token, commit_hash) {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!(
+                        "Cached repo at {} is unusable, cloning again: {}",
+                        cached_path, e
+                    );
+                    remove_temp_dir(&cached_path)?;
+                    false
+                }
+            }
+        };
+        if !updated {
+            std::fs::create_dir_all(cache_dir)
+                .map_err(|e| anyhow!("Failed to create cache dir {}: {}", cache_dir, e))?;
+            info!("Cloning repo into: {}", cached_path);
+            clone_into(
+                repo_url,
+                &clone_url,
+                git_token,
+                &cached_path,
+                commit_hash,
+                clone_options.shallow,
+            )?;
+            if let Some(commit_hash) = commit_hash {
+                git_checkout(&cached_path, commit_hash)
+                    .map_err(|e| anyhow!("Encountered error in git setup: {}", e))?;
+                info!("Checked out commit hash: {}", commit_hash);
+            }
+        }
+        if clone_options.recurse_submodules {
+            git_update_submodules(&cached_path)?;
+        }
+        return Ok((None, cached_path));
+    }
+
+    let uuid = Uuid::new_v4().to_string();
+
+    // Create a temporary directory to clone the repo into
+    let verify_dir = if current_dir {
+        std::env::current_dir()?.join(format!(".{}", uuid))
+    } else {
+        std::env::temp_dir().join("solana-verify").join(uuid)
+    }
+    .to_str()
+    .ok_or_else(|| anyhow::Error::msg("Invalid path string"))?
+    .to_string();
+
+    // The temp dir is only registered for cleanup if the user didn't ask to keep it
+    if !keep_clone {
+        temp_dir_opt.replace(verify_dir.clone());
+    }
+
+    let verify_tmp_root_path = Path::new(&verify_dir)
+        .join(base_name)
+        .to_string_lossy()
+        .to_string();
+    info!("Cloning repo into: {}", verify_tmp_root_path);
+    clone_into(
+        repo_url,
+        &clone_url,
+        git_token,
+        &verify_tmp_root_path,
+        commit_hash,
+        clone_options.shallow,
+    )?;
+
+    // Checkout a specific commit hash, if provided
+    if let Some(commit_hash) = commit_hash {
+        if let Err(e) = git_checkout(&verify_tmp_root_path, commit_hash) {
+            if keep_clone {
+                print_status(format!("Retained cloned repo at: {}", verify_tmp_root_path));
+            } else {
+                remove_temp_dir(&verify_dir)?;
+            }
+            Err(anyhow!("Encountered error in git setup: {}", e))?;
+        }
+        info!("Checked out commit hash: {}", commit_hash);
+    }
+    if clone_options.recurse_submodules {
+        git_update_submodules(&verify_tmp_root_path)?;
+    }
+    if !is_quiet() {
+        print_status(format!(
+            "Cloned {} into {} at commit {}",
+            repo_url,
+            verify_tmp_root_path,
+            git_head(&verify_tmp_root_path)?
+        ));
+    }
+
+    Ok((Some(verify_dir), verify_tmp_root_path))
+}
+
+/// Clones the repository into `path`. A shallow clone only fetches `commit_hash`, and falls back to
+/// a full clone if the host doesn't allow fetching a single commit
+fn clone_into(
+    repo_url: &str,
+    clone_url: &str,
+    git_token: Option<&str>,
+    path: &str,
+    commit_hash: Option<&str>,
+    shallow: bool,
+) -> anyhow::Result<()> {
+    if let (true, Some(commit_hash)) = (shallow, commit_hash) {
+        let result = (|| -> anyhow::Result<()> {
+            git_clone(repo_url, clone_url, git_token, path, &["--depth", "1"])?;
+            let output = std::process::Command::new("git")
+                .args(["-C", path])
+                .args(["fetch", "--depth", "1", clone_url, commit_hash])
+                .output()?;
+            if !output.status.success() {
+                return Err(anyhow!(
+                    "Failed to fetch commit {}: {}",
+                    commit_hash,
+                    redact_token(String::from_utf8_lossy(&output.stderr).trim(), git_token)
+                ));
+            }
+            Ok(())
+        })();
+        match result {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                warn!("Shallow clone failed, falling back to a full clone: {}", e);
+                remove_temp_dir(path)?;
+            }
+        }
+    }
+    git_clone(repo_url, clone_url, git_token, path, &[])
+}
+
+/// Clones `clone_url` into `path`, leaving `repo_url` as the remote so no token is stored.
+/// Clones that fail on a network error are retried.
+fn git_clone(
+    repo_url: &str,
+    clone_url: &str,
+    git_token: Option<&str>,
+    path: &str,
+    extra_args: &[&str],
+) -> anyhow::Result<()> {
+    if Path::new(path).exists() {
+        return Err(anyhow!(
+            "Failed to clone {}: the destination {} already exists. Remove it, or pass --force to replace it",
+            repo_url,
+            path
+        ));
+    }
+    let mut attempt = 0;
+    loop {
+        debug!(
+            "Running git clone {} {}",
+            redact_token(clone_url, git_token),
+            path
+        );
+        let clone_output = std::process::Command::new("git")
+            .arg("clone")
+            .args(extra_args)
+            .args([clone_url, path])
+            .stdout(Stdio::inherit())
+            .output()?;
+        if clone_output.status.success() {
+            break;
+        }
+        let stderr = redact_token(
+            String::from_utf8_lossy(&clone_output.stderr).trim(),
+            git_token,
+        );
+        if attempt < GIT_CLONE_RETRIES && is_network_error(&stderr) {
+            attempt += 1;
+            let delay = Duration::from_secs(2u64.pow(attempt));
+            warn!(
+                "Failed to clone {}: {}. Retrying in {:?} ({}/{})",
+                repo_url, stderr, delay, attempt, GIT_CLONE_RETRIES
+            );
+            // A failed clone may leave a partial checkout behind, which would fail the next attempt
+            remove_temp_dir(path)?;
+            std::thread::sleep(delay);
+            continue;
+        }
+        return Err(anyhow!("Failed to clone {}: {}", repo_url, stderr));
+    }
+    if git_token.is_some() {
+        // Don't leave the token behind in the clone's git config
+        std::process::Command::new("git")
+            .args(["-C", path])
+            .args(["remote", "set-url", "origin", repo_url])
+            .output()?;
+    }
+    Ok(())
+}
+
+/// Whether git failed to reach the remote, rather than e.g. the repository not existing
+fn is_network_error(stderr: &str) -> bool {
+    [
+        "Could not resolve host",
+        "Failed to connect",
+        "Connection timed out",
+        "Connection reset",
+        "Operation timed out",
+        "early EOF",
+        "RPC failed",
+        "the remote end hung up unexpectedly",
+        "TLS connection",
+    ]
+    .iter()
+    .any(|message| stderr.contains(message))
+}
+
+/// Returns the full hash of the commit checked out in `path`
+fn git_head(path: &str) -> anyhow::Result<String> {
+    let output = std::process::Command::new("git")
+        .args(["-C", path])
+        .args(["rev-parse", "HEAD"])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to get the checked out commit: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    parse_output(output.stdout)
+}
+
+fn git_checkout(path: &str, commit_hash: &str) -> anyhow::Result<()> {
+    std::process::Command::new("git")
+        .args(["-C", path])
+        .args(["checkout", "--force", commit_hash])
+        .output()
+        .map_err(|e| anyhow!("Failed to checkout commit hash: {:?}", e))
+        .and_then(|output| {
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(anyhow!(
+                    "Failed to checkout commit hash {}: {}",
+                    commit_hash,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ))
+            }
+        })
+}
+
+/// Checks out the submodules of the checked out commit, recursively
+fn git_update_submodules(path: &str) -> anyhow::Result<()> {
+    info!("Updating submodules");
+    let output = std::process::Command::new("git")
+        .args(["-C", path])
+        .args(["submodule", "update", "--init", "--recursive", "--force"])
+        .stdout(Stdio::inherit())
+        .output()
+        .map_err(|e| anyhow!("Failed to update submodules: {:?}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to update submodules: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Fetches the latest commits into a cached clone and checks out `commit_hash`, or the head of
+/// the default branch if no commit is given
+fn update_cached_clone(
+    path: &str,
+    repo_url: &str,
+    clone_url: &str,
+    git_token: Option<&str>,
+    commit_hash: Option<&str>,
+) -> anyhow::Result<()> {
+    let git = |args: &[&str]| -> anyhow::Result<String> {
+        let output = std::process::Command::new("git")
+            .args(["-C", path])
+            .args(args)
+            .output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "git {} failed: {}",
+                args.first().unwrap_or(&""),
+                redact_token(String::from_utf8_lossy(&output.stderr).trim(), git_token)
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+
+    // Two repos with the same name would share a cache entry, so make sure it is the right one
+    let origin = git(&["remote", "get-url", "origin"])?;
+    if origin != repo_url {
+        return Err(anyhow!("it is a clone of {}", origin));
+    }
+    git(&[
+        "fetch",
+        "--tags",
+        clone_url,
+        "+refs/heads/*:refs/remotes/origin/*",
+    ])?;
+    git_checkout(path, commit_hash.unwrap_or("origin/HEAD"))?;
+    if let Some(commit_hash) = commit_hash {
+        info!("Checked out commit hash: {}", commit_hash);
+    }
+    Ok(())
+}
+
+/// Describes the on-chain side of a verification, so that a closed or missing program can be told
+/// apart from a build that simply differs
+pub fn describe_on_chain_program(
+    client: &RpcClient,
+    program_id: Pubkey,
+    buffer_address: Option<Pubkey>,
+    rpc: &RpcOptions,
+) -> String {
+    let code_size = |data: &[u8], offset: usize| {
+        data.get(offset..).map_or(0, |code| {
+            code.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1)
+        })
+    };
+
+    if let Some(buffer_address) = buffer_address {
+        return match get_account_data_with_retry(client, &buffer_address, rpc) {
+            Ok(account_data) => match code_size(
+                &account_data,
+                UpgradeableLoaderState::size_of_buffer_metadata(),
+            ) {
+                0 => format!("buffer {} holds no program data", buffer_address),
+                size => format!(
+                    "buffer {} holds {} bytes of program data",
+                    buffer_address, size
+                ),
+            },
+            Err(e) => format!("buffer {} could not be fetched ({})", buffer_address, e),
+        };
+    }
+
+    let program_account = match get_account_with_retry(client, &program_id, rpc.max_retries) {
+        Ok(account) => account,
+        Err(e) => {
+            return format!(
+                "program account {} could not be fetched, it may not exist ({})",
+                program_id, e
+            )
+        }
+    };
+    if !program_account.executable {
+        return format!("account {} is not an executable program", program_id);
+    }
+
+    let loader = match ProgramLoader::from_owner(&program_account.owner) {
+        Some(loader) => loader,
+        None => {
+            return format!(
+                "program {} is owned by {}, which is not a supported loader",
+                program_id, program_account.owner
+            )
+        }
+    };
+    let account_data = match loader {
+        ProgramLoader::Upgradeable => {
+            let programdata_address = get_programdata_address(&program_id);
+            match get_account_data_with_retry(client, &programdata_address, rpc) {
+                Ok(data) => data,
+                Err(_) => {
+                    return format!(
+                        "program {} appears to be closed, its programdata account {} is missing",
+                        program_id, programdata_address
+                    )
+                }
+            }
+        }
+        ProgramLoader::V4 | ProgramLoader::NonUpgradeable => program_account.data,
+    };
+
+    match code_size(&account_data, loader.code_offset()) {
+        0 => format!(
+            "program {} appears to have been closed, it holds no program data",
+            program_id
+        ),
+        size => format!(
+            "the on-chain program is {} bytes (without zero padding), so the build differs",
+            size
+        ),
+    }
+}
+
+/// Sends the verification job to the remote verifier and waits for its result
+#[allow(clippy::too_many_arguments)]
+pub async fn verify_remote(
+    remote_url: String,
+    connection_url: Option<String>,
+    rpc: &RpcOptions,
+    repo_url: String,
+    commit_hash: Option<String>,
+    program_id: Pubkey,
+    library_name_opt: Option<String>,
+    bpf_flag: bool,
+    relative_mount_path: String,
+    base_image: Option<String>,
+    cargo_args: Vec<String>,
+    poll_options: &PollOptions,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    let genesis_hash = get_genesis_hash(connection_url, rpc)?;
+    if genesis_hash != MAINNET_GENESIS_HASH {
+        return Err(anyhow!("Remote verification only works with mainnet. Please use verify-from-repo without the --remote flag to verify locally."));
+    }
+
+    if !is_quiet() && output == OutputFormat::Text {
+        println!("Sending verify command to remote machine...");
+    }
+    send_job_to_remote(
+        &remote_url,
+        &repo_url,
+        &commit_hash,
+        &program_id,
+        &library_name_opt,
+        bpf_flag,
+        relative_mount_path,
+        base_image,
+        cargo_args,
+        poll_options,
+        output,
+    )
+    .await
+}
+
+/// Sends each program of a workspace to the remote verifier, as one job per program, and prints
+/// the result of each once all of them are done
+#[allow(clippy::too_many_arguments)]
+pub async fn verify_remote_programs(
+    remote_url: String,
+    connection_url: Option<String>,
+    rpc: &RpcOptions,
+    repo_url: String,
+    commit_hash: Option<String>,
+    programs: Vec<(String, Pubkey)>,
+    bpf_flag: bool,
+    relative_mount_path: String,
+    base_image: Option<String>,
+    cargo_args: Vec<String>,
+    poll_options: &PollOptions,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    let genesis_hash = get_genesis_hash(connection_url, rpc)?;
+    if genesis_hash != MAINNET_GENESIS_HASH {
+        return Err(anyhow!("Remote verification only works with mainnet. Please use verify-workspace to verify locally."));
+    }
+
+    if !is_quiet() && output == OutputFormat::Text {
+        println!(
+            "Sending {} verify commands to remote machine...",
+            programs.len()
+        );
+    }
+    let results = send_jobs_to_remote(
+        &remote_url,
+        &repo_url,
+        &commit_hash,
+        &programs,
+        bpf_flag,
+        &relative_mount_path,
+        &base_image,
+        &cargo_args,
+        poll_options,
+        output,
+    )
+    .await?;
+    print_workspace_results(&results, output)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn build_and_verify_repo(
+    mount_path: String,
+    build_options: &BuildOptions,
+    library_name: String,
+    connection_url: Option<String>,
+    program_id: Pubkey,
+    buffer_address: Option<Pubkey>,
+    compare_file: Option<&str>,
+    cargo_args: Vec<String>,
+    rpc: &RpcOptions,
+    container_id_opt: &mut Option<String>,
+) -> anyhow::Result<(String, String, usize, Option<BuildInfo>)> {
+    // Build the code using the docker container
+    let build_info = build(
+        Some(mount_path.clone()),
+        Some(library_name.clone()),
+        build_options,
+        false,
+        false,
+        cargo_args,
+        container_id_opt,
+    )?;
+
+    // Get the hash of the build
+    let executable_path =
+        find_executable_in(&deploy_dir(&mount_path, build_options), Some(&library_name))?;
+    info!("Executable file found at path: {:?}", executable_path);
+    if let Ok(metadata) = std::fs::metadata(&executable_path) {
+        debug!("Executable is {} bytes", metadata.len());
+    }
+    check_sbf_executable(&executable_path)?;
+    let build_hash = get_file_hash(&executable_path.to_string_lossy())?;
+
+    // Get the hash and size of the dump file or the pending upgrade buffer if one was given,
+    // otherwise the deployed program
+    let (program_hash, program_size) = if let Some(compare_file) = compare_file {
+        info!("Hashing file to compare against: {}", compare_file);
+        get_file_hash_and_size(compare_file)?
+    } else {
+        let program_data = if let Some(buffer_address) = buffer_address {
+            info!(
+                "Fetching on-chain buffer data for buffer address: {}",
+                buffer_address,
+            );
+            get_buffer_data(connection_url, buffer_address, rpc)?
+        } else {
+            info!(
+                "Fetching on-chain program data for program ID: {}",
+                program_id,
+            );
+            get_program_data(connection_url, program_id, rpc)?
+        };
+        (
+            get_binary_hash(&program_data),
+            get_trimmed_size(&program_data),
+        )
+    };
+
+    Ok((build_hash, program_hash, program_size, build_info))
+}
+
+/// Accepts either a lib name or the directory of a program relative to `mount_path`, such as
+/// `programs/my_program`, whose Cargo.toml then names the lib
+pub fn resolve_library_name(mount_path: &Path, library_name: &str) -> anyhow::Result<String> {
+    let manifest = mount_path.join(library_name).join("Cargo.toml");
+    if !manifest.is_file() {
+        return Ok(library_name.to_string());
+    }
+    let lib_name = get_lib_name_from_cargo_toml(&manifest.to_string_lossy())?;
+    info!("Using lib name {} of {}", lib_name, manifest.display());
+    Ok(lib_name)
+}
+
+/// Locates the built program in `<mount_path>/target/deploy`. If more than one `.so` was built,
+/// `library_name` is required to pick the right one.
+pub fn find_executable(mount_path: &str, library_name: Option<&str>) -> anyhow::Result<PathBuf> {
+    find_executable_in(
+        &PathBuf::from(mount_path).join("target").join("deploy"),
+        library_name,
+    )
+}
+
+/// Locates the built program in `deploy_dir`, like [`find_executable`]
+pub fn find_executable_in(
+    deploy_dir: &Path,
+    library_name: Option<&str>,
+) -> anyhow::Result<PathBuf> {
+    let mut candidates = std::fs::read_dir(deploy_dir)
+        .map_err(|e| anyhow!("Failed to read {}: {}", deploy_dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "so"))
+        .collect::<Vec<_>>();
+    candidates.sort();
+
+    let names = candidates
+        .iter()
+        .filter_map(|path| path.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+
+    if let Some(library_name) = library_name {
+        let filename = format!("{}.so", library_name);
+        return candidates
+            .into_iter()
+            .find(|path| path.file_name().is_some_and(|name| *name == *filename))
+            .ok_or_else(|| {
+                anyhow!(
+                    "No executable named {} found in {}. Found: {:?}",
+                    filename,
+                    deploy_dir.display(),
+                    names
+                )
+            });
+    }
+
+    match candidates.len() {
+        0 => Err(anyhow!("No executable found in {}", deploy_dir.display())),
+        1 => Ok(candidates.remove(0)),
+        _ => Err(anyhow!(
+            "Found multiple executables in {}: {:?}. Please specify the target with the --library-name <name> option",
+            deploy_dir.display(),
+            names
+        )),
+    }
+}
+
+/// Recursively collects the Cargo.toml files under `root`, skipping build output and hidden
+/// directories such as `.git`
+pub fn find_cargo_tomls(root: &Path) -> Vec<PathBuf> {
+    let mut manifests = vec![];
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if path.is_dir() {
+                if name != "target" && !name.starts_with('.') {
+                    dirs.push(path);
+                }
+            } else if name == "Cargo.toml" {
+                manifests.push(path);
+            }
+        }
+    }
+    manifests.sort();
+    manifests
+}
+
+/// Removes a temporary directory and everything in it. A directory that is already gone is not an error
+pub fn remove_temp_dir(path: &str) -> std::io::Result<()> {
+    match std::fs::remove_dir_all(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+pub fn print_verification_result(result: &VerificationResult) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string(result)?);
+    Ok(())
+}
+
+pub fn parse_output(output: Vec<u8>) -> anyhow::Result<String> {
+    let parsed_output = String::from_utf8(output)?
+        .strip_suffix("\n")
+        .ok_or_else(|| anyhow!("Failed to parse output"))?
+        .to_string();
+    Ok(parsed_output)
+}
+
+pub fn get_pkg_version_from_cargo_lock(
+    package_name: &str,
+    cargo_lock_file: &str,
+) -> anyhow::Result<(u32, u32, u32)> {
+    let lockfile = Lockfile::load(cargo_lock_file)
+        .map_err(|e| anyhow!("Failed to read {}: {}", cargo_lock_file, e))?;
+    let res = lockfile
+        .packages
+        .iter()
+        .filter(|pkg| pkg.name.to_string() == *package_name)
+        .filter_map(|pkg| {
+            let version = pkg.version.clone().to_string();
+            let version_parts: Vec<&str> = version.split(".").collect();
+            if version_parts.len() == 3 {
+                let major = version_parts[0].parse::<u32>().unwrap_or(0);
+                let minor = version_parts[1].parse::<u32>().unwrap_or(0);
+                let patch = version_parts[2].parse::<u32>().unwrap_or(0);
+                return Some((major, minor, patch));
+            }
+            None
+        })
+        .next()
+        .ok_or_else(|| anyhow!("Failed to parse {} version from Cargo.lock", package_name))?;
+    Ok(res)
+}
+
+pub fn get_lib_name_from_cargo_toml(cargo_toml_file: &str) -> anyhow::Result<String> {
+    let manifest = Manifest::from_path(cargo_toml_file)
+        .map_err(|e| anyhow!("Failed to read {}: {}", cargo_toml_file, e))?;
+    let lib = manifest
+        .lib
+        .ok_or_else(|| anyhow!("Failed to parse lib from Cargo.toml"))?;
+    lib.name
+        .ok_or_else(|| anyhow!("Failed to parse lib name from Cargo.toml"))
+}
+
+pub fn get_pkg_name_from_cargo_toml(cargo_toml_file: &str) -> Option<String> {
+    let manifest = Manifest::from_path(cargo_toml_file).ok()?;
+    let pkg = manifest.package?;
+    Some(pkg.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Serves accounts from memory instead of a node
+    #[derive(Default)]
+    struct FakeFetcher {
+        accounts: HashMap<Pubkey, Account>,
+        slices_read: std::cell::Cell<usize>,
+    }
+
+    impl FakeFetcher {
+        fn insert(&mut self, key: Pubkey, owner: Pubkey, data: Vec<u8>) {
+            let account = Account {
+                lamports: 1,
+                data,
+                owner,
+                executable: false,
+                rent_epoch: 0,
+            };
+            self.accounts.insert(key, account);
+        }
+    }
+
+    impl AccountFetcher for FakeFetcher {
+        fn get_account(&self, key: &Pubkey) -> anyhow::Result<Account> {
+            self.accounts
+                .get(key)
+                .cloned()
+                .ok_or_else(|| VerifyError::AccountNotFound(*key).into())
+        }
+
+        fn get_account_data_slice(
+            &self,
+            key: &Pubkey,
+            offset: usize,
+            length: usize,
+            _min_context_slot: Option<u64>,
+        ) -> anyhow::Result<(Vec<u8>, u64)> {
+            self.slices_read.set(self.slices_read.get() + 1);
+            let data = self.get_account_data(key)?;
+            let start = offset.min(data.len());
+            let end = (offset + length).min(data.len());
+            Ok((data[start..end].to_vec(), 1))
+        }
+    }
+
+    /// An executable with zeros inside it, which must be hashed, unlike the padding after it
+    const EXECUTABLE: &[u8] = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0program\0\0\0\0code";
+
+    /// The executable followed by the zero padding that program accounts are allocated with
+    fn padded(metadata: Vec<u8>, metadata_size: usize) -> Vec<u8> {
+        let mut data = metadata;
+        data.resize(metadata_size, 0);
+        data.extend(EXECUTABLE);
+        data.resize(data.len() + 1024, 0);
+        data
+    }
+
+    fn file_hash(data: &[u8]) -> String {
+        let path = std::env::temp_dir().join(format!("solana-verify-{}.so", Uuid::new_v4()));
+        std::fs::write(&path, data).unwrap();
+        let hash = get_file_hash(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        hash.unwrap()
+    }
+
+    fn check_executable(data: &[u8]) -> Result<(), VerifyError> {
+        let path = std::env::temp_dir().join(format!("solana-verify-{}.so", Uuid::new_v4()));
+        std::fs::write(&path, data).unwrap();
+        let result = check_sbf_executable(&path);
+        std::fs::remove_file(&path).unwrap();
+        result
+    }
+
+    const CODE: &[u8] = b"\xb7\0\0\0\x01\0\0\0\x95\0\0\0\0\0\0\0";
+    const COMMENT: &[u8] = b"Linker: LLD 15.0.4\0";
+    const BUILD_ID: &[u8] = &[0xab; 20];
+
+    /// A 64-bit ELF for `machine` with loaded code, a `.comment`, a build-id note and the section
+    /// name table. Returns it with the file ranges of those sections in that order.
+    fn elf_fixture(machine: u16) -> (Vec<u8>, Vec<std::ops::Range<usize>>) {
+        use goblin::elf::section_header::{SHF_EXECINSTR, SHT_NOTE, SHT_PROGBITS, SHT_STRTAB};
+
+        let names = b"\0.text\0.comment\0.note.gnu.build-id\0.shstrtab\0";
+        let mut elf = vec![0; 64];
+        let mut sections = vec![];
+        for (name, kind, flags, contents) in [
+            (1, SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR, CODE),
+            (7, SHT_PROGBITS, 0, COMMENT),
+            (16, SHT_NOTE, SHF_ALLOC, BUILD_ID),
+            (35, SHT_STRTAB, 0, &names[..]),
+        ] {
+            sections.push((name, kind, flags, elf.len()..elf.len() + contents.len()));
+            elf.extend(contents);
+        }
+        let section_headers = elf.len();
+        elf.extend([0; 64]);
+        for (name, kind, flags, range) in &sections {
+            elf.extend((*name as u32).to_le_bytes());
+            elf.extend(kind.to_le_bytes());
+            elf.extend(u64::from(*flags).to_le_bytes());
+            elf.extend(0u64.to_le_bytes());
+            elf.extend((range.start as u64).to_le_bytes());
+            elf.extend((range.len() as u64).to_le_bytes());
+            elf.extend([0; 24]);
+        }
+        elf[..4].copy_from_slice(b"\x7fELF");
+        elf[4] = 2; // 64-bit
+        elf[5] = 1; // little endian
+        elf[6] = 1; // ELF version
+        elf[16] = 3; // shared object
+        elf[18..20].copy_from_slice(&machine.to_le_bytes());
+        elf[20..24].copy_from_slice(&1u32.to_le_bytes());
+        elf[40..48].copy_from_slice(&(section_headers as u64).to_le_bytes());
+        elf[52..54].copy_from_slice(&64u16.to_le_bytes());
+        elf[58..60].copy_from_slice(&64u16.to_le_bytes());
+        elf[60..62].copy_from_slice(&(sections.len() as u16 + 1).to_le_bytes());
+        elf[62..64].copy_from_slice(&(sections.len() as u16).to_le_bytes());
+        (elf, sections.into_iter().map(|(.., range)| range).collect())
+    }
+
+    #[test]
+    fn rpc_url_is_logged_without_api_key() {
+        assert_eq!(
+            redact_url("https://mainnet.example.com/v2/secret?api-key=secret"),
+            "https://mainnet.example.com"
+        );
+        assert_eq!(redact_url("http://localhost:8899"), "http://localhost:8899");
+    }
+
+    #[test]
+    fn all_zeros_hash_like_no_data() {
+        assert_eq!(get_binary_hash(&[0; 4096]), get_binary_hash(&[]));
+        assert_eq!(get_trimmed_size(&[0; 4096]), 0);
+    }
+
+    #[test]
+    fn interior_zeros_are_hashed() {
+        assert_eq!(trim_zero_padding(b"a\0\0\0b"), b"a\0\0\0b");
+        assert_ne!(get_binary_hash(b"a\0\0\0b"), get_binary_hash(b"ab"));
+    }
+
+    #[test]
+    fn only_trailing_padding_is_trimmed() {
+        assert_eq!(trim_zero_padding(b"\0a\0b\0\0\0"), b"\0a\0b");
+        assert_eq!(get_binary_hash(b"\0a\0b\0\0\0"), get_binary_hash(b"\0a\0b"));
+        assert_eq!(get_binary_hash(b"\0a\0b"), sha256::digest(&b"\0a\0b"[..]));
+    }
+
+    #[test]
+    fn trimmed_hasher_matches_get_binary_hash() {
+        let data = b"\0a\0\0b\0\0\0";
+        let mut hasher = TrimmedHasher::default();
+        for chunk in data.chunks(3) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.trimmed_len(), get_trimmed_size(data));
+        assert_eq!(hasher.finalize(), get_binary_hash(data));
+    }
+
+    #[test]
+    fn program_and_buffer_hashes_match_the_file_hash() {
+        let program_id = Pubkey::new_unique();
+        let programdata_address = get_programdata_address(&program_id);
+        let buffer_address = Pubkey::new_unique();
+        let bare_program_id = Pubkey::new_unique();
+        let mut fetcher = FakeFetcher::default();
+        fetcher.insert(
+            program_id,
+            bpf_loader_upgradeable::id(),
+            bincode::serialize(&UpgradeableLoaderState::Program {
+                programdata_address,
+            })
+            .unwrap(),
+        );
+        fetcher.insert(
+            programdata_address,
+            bpf_loader_upgradeable::id(),
+            padded(
+                bincode::serialize(&UpgradeableLoaderState::ProgramData {
+                    slot: 1,
+                    upgrade_authority_address: Some(Pubkey::new_unique()),
+                })
+                .unwrap(),
+                UpgradeableLoaderState::size_of_programdata_metadata(),
+            ),
+        );
+        fetcher.insert(
+            buffer_address,
+            bpf_loader_upgradeable::id(),
+            padded(
+                bincode::serialize(&UpgradeableLoaderState::Buffer {
+                    authority_address: Some(Pubkey::new_unique()),
+                })
+                .unwrap(),
+                UpgradeableLoaderState::size_of_buffer_metadata(),
+            ),
+        );
+        fetcher.insert(bare_program_id, bpf_loader::id(), padded(vec![], 0));
+
+        let file_hash = file_hash(EXECUTABLE);
+        assert_eq!(file_hash, get_binary_hash(EXECUTABLE));
+        assert_eq!(
+            on_chain_program_hash(&fetcher, program_id).unwrap(),
+            file_hash
+        );
+        assert_eq!(
+            on_chain_program_hash(&fetcher, bare_program_id).unwrap(),
+            file_hash
+        );
+        let buffer_data = buffer_program_data(&fetcher, buffer_address).unwrap();
+        assert_eq!(get_binary_hash(&buffer_data), file_hash);
+    }
+
+    #[test]
+    fn chunks_reassemble_the_account_data() {
+        let key = Pubkey::new_unique();
+        let data = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut fetcher = FakeFetcher::default();
+        fetcher.insert(key, bpf_loader_upgradeable::id(), data.clone());
+        let read = get_account_data_in_chunks(&fetcher, &key, 4096, 0, data.len()).unwrap();
+        assert_eq!(read, data);
+        assert_eq!(fetcher.slices_read.get(), data.len() / 4096 + 1);
+    }
+
+    #[test]
+    fn chunks_stop_at_the_maximum_account_size() {
+        let key = Pubkey::new_unique();
+        let mut fetcher = FakeFetcher::default();
+        fetcher.insert(key, bpf_loader_upgradeable::id(), vec![1; 100_000]);
+        let error = get_account_data_in_chunks(&fetcher, &key, 4096, 0, 50_000).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("more than the maximum of 50000 bytes"));
+        assert!(fetcher.slices_read.get() < 100_000 / 4096);
+    }
+
+    #[test]
+    fn chunks_of_a_missing_account_are_not_retried() {
+        let fetcher = FakeFetcher::default();
+        let error = get_account_data_in_chunks(&fetcher, &Pubkey::new_unique(), 4096, 3, 50_000)
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<VerifyError>(),
+            Some(VerifyError::AccountNotFound(_))
+        ));
+        assert_eq!(fetcher.slices_read.get(), 1);
+    }
+
+    #[test]
+    fn closed_program_is_reported_as_closed() {
+        let program_id = Pubkey::new_unique();
+        let mut fetcher = FakeFetcher::default();
+        fetcher.insert(
+            program_id,
+            bpf_loader_upgradeable::id(),
+            bincode::serialize(&UpgradeableLoaderState::Program {
+                programdata_address: get_programdata_address(&program_id),
+            })
+            .unwrap(),
+        );
+        let error = on_chain_program_hash(&fetcher, program_id).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<VerifyError>(),
+            Some(VerifyError::ProgramClosed(_))
+        ));
+    }
+
+    #[test]
+    fn ebpf_and_sbf_executables_are_accepted() {
+        check_executable(&elf_fixture(EM_BPF).0).unwrap();
+        check_executable(&elf_fixture(EM_SBF).0).unwrap();
+    }
+
+    #[test]
+    fn host_executables_are_rejected() {
+        for data in [
+            elf_fixture(goblin::elf::header::EM_X86_64).0,
+            elf_fixture(goblin::elf::header::EM_AARCH64).0,
+            b"not an ELF".to_vec(),
+        ] {
+            assert!(matches!(
+                check_executable(&data),
+                Err(VerifyError::InvalidExecutable { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn normalize_elf_only_zeroes_what_is_not_loaded() {
+        let (mut elf, ranges) = elf_fixture(EM_SBF);
+        let original = elf.clone();
+        normalize_elf(&mut elf).unwrap();
+        let (code, comment, build_id) = (&ranges[0], &ranges[1], &ranges[2]);
+        assert_eq!(&elf[code.clone()], CODE);
+        assert!(elf[comment.clone()].iter().all(|&b| b == 0));
+        assert!(elf[build_id.clone()].iter().all(|&b| b == 0));
+        assert_eq!(elf[..64], original[..64]);
+        assert_eq!(elf[ranges[3].end..], original[ranges[3].end..]);
+    }
+
+    #[test]
+    fn normalized_hash_ignores_the_build_id_and_comment() {
+        let (elf, ranges) = elf_fixture(EM_SBF);
+        let mut rebuilt = elf.clone();
+        rebuilt[ranges[1].start] = b'l';
+        rebuilt[ranges[2].clone()].fill(0xcd);
+        assert_ne!(get_binary_hash(&elf), get_binary_hash(&rebuilt));
+        assert_eq!(
+            get_normalized_binary_hash(&elf).unwrap(),
+            get_normalized_binary_hash(&rebuilt).unwrap()
+        );
+    }
+
+    #[test]
+    fn remote_repo_urls_are_accepted() {
+        for url in [
+            "https://github.com/solana-labs/example",
+            "http://example.com/repo.git",
+            "ssh://git@github.com/org/repo.git",
+            "git://example.com/repo.git",
+            "git@github.com:org/repo.git",
+        ] {
+            assert_eq!(parse_repo_url(url).unwrap(), url);
+        }
+    }
+
+    #[test]
+    fn local_and_option_like_repo_urls_are_rejected() {
+        for url in [
+            "--upload-pack=touch /tmp/pwned",
+            "-c",
+            "ext::sh -c touch% /tmp/pwned",
+            "ext::ssh",
+            "file:///etc",
+            "/home/user/repo",
+            "../repo",
+            "https://github.com/org/repo foo",
+            "https://github.com/org/repo\n",
+            "https://github.com/org/repo;rm",
+            "host:repo",
+            "git@host:",
+            "git@host/path:repo",
+        ] {
+            assert!(parse_repo_url(url).is_err(), "{} was accepted", url);
+        }
+    }
+}
@@ -1,51 +1,188 @@
-use anyhow::anyhow;
-use cargo_lock::Lockfile;
-use cargo_toml::Manifest;
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Args, Command, CommandFactory, FromArgMatches, Parser, Subcommand};
+use log::LevelFilter;
+#[cfg(unix)]
 use signal_hook::{
     consts::{SIGINT, SIGTERM},
     iterator::Signals,
 };
-use solana_cli_config::{Config, CONFIG_FILE};
-use solana_client::rpc_client::RpcClient;
-use solana_sdk::{
-    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
-    pubkey::Pubkey,
+use solana_sdk::{pubkey::Pubkey, signature::read_keypair_file};
+use solana_verify::{
+    api_client::{cancel_remote_job, parse_remote_url, resume_remote_job, REMOTE_SERVER_URL},
+    build, check_at_slot, check_expected_hash, check_min_slot, compare_files,
+    config_file::ConfigFile,
+    get_binary_hash, get_buffer_data, get_client, get_file_hash_and_size,
+    get_normalized_binary_hash, get_programdata_data, get_trimmed_size, hash_programs, list_images,
+    on_chain_program_data, parse_expected_hash, parse_image_reference, parse_mount_path,
+    parse_program_spec, parse_repo_url, print_file_comparison, print_image_verification,
+    print_status, remove_temp_dir, selfcheck, set_json_output, set_plain_output, set_quiet,
+    set_status_on_stderr, verify_from_image, verify_from_repo, verify_remote,
+    verify_remote_programs, verify_workspace, write_hash_file, BuildOptions, CloneOptions,
+    OutputFormat, PollOptions, ProgramIdOptions, RpcFetcher, RpcOptions,
 };
-use std::{
-    io::Read,
-    path::PathBuf,
-    process::Stdio,
-    sync::atomic::AtomicBool,
-    sync::{atomic::Ordering, Arc},
+use std::io::IsTerminal;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
 };
-use uuid::Uuid;
-pub mod api_client;
-pub mod image_config;
-pub mod api_models;
-use image_config::IMAGE_MAP;
-
-use crate::api_client::send_job_to_remote;
-
-const MAINNET_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
-
-pub fn get_network(network_str: &str) -> &str {
-    match network_str {
-        "devnet" | "dev" | "d" => "https://api.devnet.solana.com",
-        "mainnet" | "main" | "m" | "mainnet-beta" => "https://api.mainnet-beta.solana.com",
-        "localnet" | "localhost" | "l" | "local" => "http://localhost:8899",
-        _ => network_str,
-    }
-}
 
 #[derive(Parser, Debug)]
 #[clap(author = "Ellipsis", version, about)]
 struct Arguments {
     #[clap(subcommand)]
     subcommand: SubCommand,
-    /// Optionally include your RPC endpoint. Use "local", "dev", "main" for default endpoints. Defaults to your Solana CLI config file.
-    #[clap(global = true, short, long)]
+    /// Optionally include your RPC endpoint. Use "mainnet", "devnet", "testnet" or "localhost" (or "m", "d", "t", "l" as with the
+    /// Solana CLI) for default endpoints. Defaults to your Solana CLI config file.
+    #[clap(global = true, short, long, visible_alias = "network")]
     url: Option<String>,
+    /// Format of the verification result printed to stdout. Remote jobs print one JSON object
+    /// per line as they progress
+    #[clap(global = true, long, value_enum, default_value = "text")]
+    output: OutputFormat,
+    /// Increase logging verbosity: -v for progress, -vv for docker commands, RPC URLs and sizes
+    #[clap(global = true, short, long, action = ArgAction::Count)]
+    verbose: u8,
+    /// Only print errors and the resulting hashes or verdict, e.g. to capture the output in a script
+    #[clap(global = true, short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Print plain ASCII instead of emoji. This is the default when stdout isn't a terminal or
+    /// NO_COLOR is set
+    #[clap(global = true, long, alias = "no-emoji")]
+    no_color: bool,
+    /// Read defaults for --url, --base-image and --commitment from this TOML file instead of
+    /// ~/.config/solana-verify/config.toml
+    #[clap(global = true, long, value_name = "PATH")]
+    config: Option<String>,
+}
+
+/// Finds the `--config` argument before the arguments are parsed, since the config file provides
+/// defaults for the parser
+fn config_path_arg() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        } else if arg == "--config" {
+            return args.next();
+        } else if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(path.to_string());
+        }
+    }
+    None
+}
+
+/// Makes the values of the config file the defaults of the options they set, in every subcommand
+fn with_config_defaults(mut command: Command, config: &ConfigFile) -> Command {
+    for (id, value) in [
+        ("url", &config.rpc_url),
+        ("base_image", &config.base_image),
+        ("commitment", &config.commitment),
+    ] {
+        if let Some(value) = value {
+            if command.get_arguments().any(|arg| arg.get_id() == id) {
+                command = command.mut_arg(id, |arg| arg.default_value(value.clone()));
+            }
+        }
+    }
+    let subcommands = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .collect::<Vec<_>>();
+    for name in subcommands {
+        command =
+            command.mut_subcommand(name, |subcommand| with_config_defaults(subcommand, config));
+    }
+    command
+}
+
+#[derive(Subcommand, Debug)]
+enum HashCommand {
+    /// Get the hash of a program binary from an executable file
+    File(FileHashArgs),
+    /// Get the hash of a program binary from the deployed on-chain program
+    Program(ProgramHashArgs),
+    /// Get the hashes of several deployed programs at once, fetching them concurrently
+    Programs(ProgramHashesArgs),
+    /// Get the hash of a program binary from the deployed buffer address
+    Buffer(BufferHashArgs),
+    /// Get the hash of a program binary from its programdata account, without deriving the address
+    ProgramData(ProgramDataHashArgs),
+}
+
+/// Options shared by every way of hashing a program binary
+#[derive(Args, Debug)]
+struct HashOptions {
+    /// Fail unless the computed hash equals this hex encoded SHA-256 hash
+    #[clap(long, value_parser = parse_expected_hash)]
+    expected_hash: Option<String>,
+    /// Zero the build-id and non-loadable ELF sections, such as `.comment` and `.debug_*`, before
+    /// hashing. This tells a newer toolchain's metadata apart from a real code difference.
+    /// Normalized hashes can only be compared with other normalized hashes
+    #[clap(long, alias = "exclude-buildid", default_value = "false")]
+    normalize_elf: bool,
+    /// Also write the hash to this file, without a trailing newline
+    #[clap(long, value_name = "PATH")]
+    output_hash_file: Option<String>,
+    /// Also print the size of the program without zero padding, the part that is hashed. Programs
+    /// of different sizes can't match
+    #[clap(long, default_value = "false")]
+    show_size: bool,
+}
+
+#[derive(Args, Debug)]
+struct FileHashArgs {
+    /// Path to the executable solana program
+    filepath: String,
+    #[clap(flatten)]
+    hash_options: HashOptions,
+}
+
+#[derive(Args, Debug)]
+struct ProgramHashArgs {
+    /// The Program ID of the program to verify
+    program_id: Pubkey,
+    #[clap(flatten)]
+    rpc: RpcOptions,
+    /// Also print which loader the program was detected to be deployed with. The upgradeable
+    /// loader, loader v4 and the non-upgradeable loaders are all supported
+    #[clap(long, default_value = "false")]
+    all_loaders: bool,
+    #[clap(flatten)]
+    hash_options: HashOptions,
+}
+
+#[derive(Args, Debug)]
+struct ProgramHashesArgs {
+    /// The Program IDs of the programs to hash
+    #[clap(required = true)]
+    program_ids: Vec<Pubkey>,
+    #[clap(flatten)]
+    rpc: RpcOptions,
+    /// Maximum number of programs to fetch at the same time
+    #[clap(long, default_value = "8", value_parser = clap::value_parser!(u64).range(1..))]
+    jobs: u64,
+    /// Also list the size of each program without zero padding, the part that is hashed
+    #[clap(long, default_value = "false")]
+    show_size: bool,
+}
+
+#[derive(Args, Debug)]
+struct BufferHashArgs {
+    /// Address of the buffer account containing the deployed program data
+    buffer_address: Pubkey,
+    #[clap(flatten)]
+    rpc: RpcOptions,
+    #[clap(flatten)]
+    hash_options: HashOptions,
+}
+
+#[derive(Args, Debug)]
+struct ProgramDataHashArgs {
+    /// Address of the programdata account of an upgradeable program
+    programdata_address: Pubkey,
+    #[clap(flatten)]
+    rpc: RpcOptions,
+    #[clap(flatten)]
+    hash_options: HashOptions,
 }
 
 #[derive(Subcommand, Debug)]
@@ -54,848 +191,827 @@ enum SubCommand {
     Build {
         /// Path to mount to the docker image
         mount_directory: Option<String>,
-        /// Which binary file to build (applies to repositories with multiple programs)
+        /// Which binary file to build (applies to repositories with multiple programs). Either the lib
+        /// name or the directory of the program, such as `programs/my_program`
         #[clap(long)]
         library_name: Option<String>,
-        /// Optionally specify a custom base docker image to use for building the program repository
-        #[clap(short, long)]
-        base_image: Option<String>,
-        /// If the program requires cargo build-bpf (instead of cargo build-sbf), as for anchor program, set this flag
+        #[clap(flatten)]
+        build_options: BuildOptions,
+        /// Fail if the base image is a mutable tag instead of a pinned `repo@sha256:...` digest
         #[clap(long, default_value = "false")]
-        bpf: bool,
-        /// Arguments to pass to the underlying `cargo build-bpf` command
+        require_digest: bool,
+        /// Print the docker command that would run the build, without running it
+        #[clap(long, default_value = "false")]
+        print_command: bool,
+        /// Arguments to pass to the underlying `cargo build-sbf` (or `cargo build-bpf`) command
         #[clap(required = false, last = true)]
         cargo_args: Vec<String>,
     },
     /// Verifies a cached build from a docker image
     VerifyFromImage {
-        /// Path to the executable solana program within the source code repository in the docker image
+        /// Path to the executable solana program within the source code repository in the docker image.
+        /// May be a glob such as `target/deploy/*.so` that matches exactly one file
         #[clap(short, long)]
         executable_path_in_image: String,
         /// Image that contains the source code to be verified
-        #[clap(short, long)]
+        #[clap(short, long, value_parser = parse_image_reference)]
         image: String,
-        /// The Program ID of the program to verify
-        #[clap(short, long)]
-        program_id: Pubkey,
+        #[clap(flatten)]
+        program: ProgramIdOptions,
+        /// Compare against a buffer account (e.g. a pending upgrade) instead of the deployed program data
+        #[clap(long, conflicts_with = "expected_hash")]
+        buffer_address: Option<Pubkey>,
         /// Verify in current directory
         #[clap(long, default_value = "false")]
         current_dir: bool,
+        #[clap(flatten)]
+        rpc: RpcOptions,
+        /// Compare the executable against this hex encoded SHA-256 hash instead of the on-chain
+        /// program, so the image can be verified without RPC access
+        #[clap(long, value_parser = parse_expected_hash)]
+        expected_hash: Option<String>,
+        /// Fail unless the on-chain program's upgrade authority is this address
+        #[clap(long, alias = "expected-authority", value_name = "PUBKEY")]
+        check_authority: Option<Pubkey>,
+        /// Also write the hash of the executable to this file, without a trailing newline
+        #[clap(long, value_name = "PATH")]
+        output_hash_file: Option<String>,
     },
-    /// Get the hash of a program binary from an executable file
-    GetExecutableHash {
-        /// Path to the executable solana program
-        filepath: String,
+    /// Get the hash of a program binary from a file or an on-chain account
+    Hash {
+        #[clap(subcommand)]
+        command: HashCommand,
     },
+    /// Get the hash of a program binary from an executable file
+    #[clap(hide = true)]
+    GetExecutableHash(FileHashArgs),
     /// Get the hash of a program binary from the deployed on-chain program
-    GetProgramHash {
-        /// The Program ID of the program to verify
-        program_id: Pubkey,
-    },
+    #[clap(hide = true)]
+    GetProgramHash(ProgramHashArgs),
     /// Get the hash of a program binary from the deployed buffer address
-    GetBufferHash {
-        /// Address of the buffer account containing the deployed program data
-        buffer_address: Pubkey,
+    #[clap(hide = true)]
+    GetBufferHash(BufferHashArgs),
+    /// Get the hash of a program binary from its programdata account, without deriving the address
+    #[clap(hide = true)]
+    GetProgramDataHash(ProgramDataHashArgs),
+    /// Compare the hashes of two executable files, e.g. a dumped program and a local build
+    #[clap(visible_alias = "diff-hashes")]
+    DiffFiles {
+        /// Path to the first executable
+        a: String,
+        /// Path to the second executable
+        b: String,
+        /// Also print the offset of the first byte that differs
+        #[clap(long, default_value = "false")]
+        bytes: bool,
     },
     /// Builds and verifies a program from a given repository URL and a program ID
     VerifyFromRepo {
         /// Send the verify command to a remote machine
         #[clap(long, default_value = "false")]
         remote: bool,
+        /// URL of the remote verification server to use with --remote
+        #[clap(long, default_value = REMOTE_SERVER_URL, value_parser = parse_remote_url)]
+        remote_url: String,
+        #[clap(flatten)]
+        poll_options: PollOptions,
         /// Relative path to the root directory or the source code repository from which to build the program
         /// This should be the directory that contains the workspace Cargo.toml and the Cargo.lock file
-        #[clap(long, default_value = "")]
+        #[clap(long, default_value = "", value_parser = parse_mount_path)]
         mount_path: String,
-        /// The HTTPS URL of the repo to clone
+        /// The HTTPS or SSH URL of the repo to clone. SSH URLs (git@...) are cloned using your ssh-agent
+        #[clap(value_parser = parse_repo_url)]
         repo_url: String,
-        /// Optional commit hash to checkout
+        /// Access token used to clone a private HTTPS repository
         #[clap(long)]
+        git_token: Option<String>,
+        /// Optional commit hash to checkout
+        #[clap(short, long)]
         commit_hash: Option<String>,
-        /// The Program ID of the program to verify
+        #[clap(flatten)]
+        program: ProgramIdOptions,
+        /// Compare against a buffer account (e.g. a pending upgrade) instead of the deployed program data
         #[clap(long)]
-        program_id: Pubkey,
-        /// Optionally specify a custom base docker image to use for building the program repository
-        #[clap(short, long)]
-        base_image: Option<String>,
+        buffer_address: Option<Pubkey>,
+        /// Compare against a program dumped to this file, e.g. with `solana program dump`, instead of
+        /// fetching the program over RPC
+        #[clap(long, value_name = "PATH", conflicts_with = "buffer_address")]
+        compare_file: Option<String>,
         /// If the repo_url points to a repo that contains multiple programs, specify the name of the library name of the program to
         /// build and verify. You will also need to specify the library_name if the program is not part of the top-level Cargo.toml
-        /// Otherwise it will be inferred from the Cargo.toml file
+        /// Otherwise it will be inferred from the Cargo.toml file. The directory of the program, such as `programs/my_program`,
+        /// may be given instead, and the executable is then looked up as `target/deploy/<lib name>.so`
         #[clap(long)]
         library_name: Option<String>,
-        /// If the program requires cargo build-bpf (instead of cargo build-sbf), as for an Anchor program, set this flag
+        #[clap(flatten)]
+        build_options: BuildOptions,
+        /// Verify in current directory
         #[clap(long, default_value = "false")]
-        bpf: bool,
+        current_dir: bool,
+        /// Don't delete the cloned repository after verifying, so the built source can be inspected
+        #[clap(long, default_value = "false")]
+        keep_clone: bool,
+        #[clap(flatten)]
+        clone_options: CloneOptions,
+        #[clap(flatten)]
+        rpc: RpcOptions,
+        /// Fail unless the hash of the build equals this hex encoded SHA-256 hash
+        #[clap(long, value_parser = parse_expected_hash)]
+        expected_hash: Option<String>,
+        /// Fail unless the on-chain program's upgrade authority is this address. It is checked
+        /// before building too, so that a program you don't control fails without a build
+        #[clap(long, alias = "expected-authority", value_name = "PUBKEY")]
+        check_authority: Option<Pubkey>,
+        /// On a hash mismatch, download the on-chain program and report where it starts to differ
+        /// from the build
+        #[clap(long, default_value = "false")]
+        explain: bool,
+        /// Write a JSON report of the verification to this path, e.g. to publish with a release
+        #[clap(long, value_name = "PATH")]
+        report: Option<String>,
+        /// Sign the report with this keypair file once the program is verified, writing the
+        /// signature to `<report>.sig` so others can check who published it
+        #[clap(long, value_name = "KEYPAIR", requires = "report")]
+        sign_with: Option<String>,
+        /// Also write the hash of the build to this file, without a trailing newline
+        #[clap(long, value_name = "PATH")]
+        output_hash_file: Option<String>,
+        /// Copy the built executable to `<DIR>/<program ID>.so`, replacing an earlier copy, to keep
+        /// it once the cloned repository is removed
+        #[clap(long, value_name = "DIR")]
+        output_dir: Option<String>,
+        /// Arguments to pass to the underlying `cargo build-sbf` (or `cargo build-bpf`) command
+        #[clap(required = false, last = true)]
+        cargo_args: Vec<String>,
+    },
+    /// Builds a program from a repository and verifies it against the buffer of a staged upgrade,
+    /// so the upgrade can be confirmed to be the expected build before it is deployed
+    VerifyPendingUpgrade {
+        /// Relative path to the root directory or the source code repository from which to build the program
+        /// This should be the directory that contains the workspace Cargo.toml and the Cargo.lock file
+        #[clap(long, default_value = "", value_parser = parse_mount_path)]
+        mount_path: String,
+        /// The HTTPS or SSH URL of the repo to clone. SSH URLs (git@...) are cloned using your ssh-agent
+        #[clap(value_parser = parse_repo_url)]
+        repo_url: String,
+        /// Access token used to clone a private HTTPS repository
+        #[clap(long)]
+        git_token: Option<String>,
+        /// Optional commit hash to checkout
+        #[clap(short, long)]
+        commit_hash: Option<String>,
+        #[clap(flatten)]
+        program: ProgramIdOptions,
+        /// The buffer account holding the staged upgrade, as written by `solana program write-buffer`
+        #[clap(long)]
+        buffer_address: Pubkey,
+        /// If the repo_url points to a repo that contains multiple programs, specify the name of the library name of the program to
+        /// build and verify. The directory of the program, such as `programs/my_program`, may be given instead
+        #[clap(long)]
+        library_name: Option<String>,
+        #[clap(flatten)]
+        build_options: BuildOptions,
+        /// Verify in current directory
+        #[clap(long, default_value = "false")]
+        current_dir: bool,
+        /// Don't delete the cloned repository after verifying, so the built source can be inspected
+        #[clap(long, default_value = "false")]
+        keep_clone: bool,
+        #[clap(flatten)]
+        clone_options: CloneOptions,
+        #[clap(flatten)]
+        rpc: RpcOptions,
+        /// Fail unless the hash of the build equals this hex encoded SHA-256 hash
+        #[clap(long, value_parser = parse_expected_hash)]
+        expected_hash: Option<String>,
+        /// On a hash mismatch, download the buffer and report where it starts to differ from the
+        /// build
+        #[clap(long, default_value = "false")]
+        explain: bool,
+        /// Write a JSON report of the verification to this path
+        #[clap(long, value_name = "PATH")]
+        report: Option<String>,
+        /// Also write the hash of the build to this file, without a trailing newline
+        #[clap(long, value_name = "PATH")]
+        output_hash_file: Option<String>,
+        /// Copy the built executable to `<DIR>/<program ID>.so`, replacing an earlier copy, to keep
+        /// it once the cloned repository is removed
+        #[clap(long, value_name = "DIR")]
+        output_dir: Option<String>,
+        /// Arguments to pass to the underlying `cargo build-sbf` (or `cargo build-bpf`) command
+        #[clap(required = false, last = true)]
+        cargo_args: Vec<String>,
+    },
+    /// Builds every program of a workspace once and verifies each against its program ID
+    VerifyWorkspace {
+        /// Relative path to the root directory or the source code repository from which to build the programs
+        /// This should be the directory that contains the workspace Cargo.toml and the Cargo.lock file
+        #[clap(long, default_value = "", value_parser = parse_mount_path)]
+        mount_path: String,
+        /// The HTTPS or SSH URL of the repo to clone. SSH URLs (git@...) are cloned using your ssh-agent
+        #[clap(value_parser = parse_repo_url)]
+        repo_url: String,
+        /// Access token used to clone a private HTTPS repository
+        #[clap(long)]
+        git_token: Option<String>,
+        /// Optional commit hash to checkout
+        #[clap(short, long)]
+        commit_hash: Option<String>,
+        /// A program to verify, as LIB_NAME:PROGRAM_ID. Repeat for each program in the workspace
+        #[clap(
+            long = "program",
+            value_name = "LIB_NAME:PROGRAM_ID",
+            required = true,
+            value_parser = parse_program_spec
+        )]
+        programs: Vec<(String, Pubkey)>,
+        #[clap(flatten)]
+        build_options: BuildOptions,
         /// Verify in current directory
         #[clap(long, default_value = "false")]
         current_dir: bool,
-        /// Arguments to pass to the underlying `cargo build-bpf` command
+        /// Don't delete the cloned repository after verifying, so the built source can be inspected
+        #[clap(long, default_value = "false")]
+        keep_clone: bool,
+        #[clap(flatten)]
+        clone_options: CloneOptions,
+        #[clap(flatten)]
+        rpc: RpcOptions,
+        /// Arguments to pass to the underlying `cargo build-sbf` (or `cargo build-bpf`) command
+        #[clap(required = false, last = true)]
+        cargo_args: Vec<String>,
+    },
+    /// Sends a program to the remote verifier to be built from a repository and verified against mainnet
+    VerifyRemote {
+        /// The HTTPS URL of the repo to clone
+        #[clap(value_parser = parse_repo_url)]
+        repo_url: String,
+        /// URL of the remote verification server
+        #[clap(long, default_value = REMOTE_SERVER_URL, value_parser = parse_remote_url)]
+        remote_url: String,
+        #[clap(flatten)]
+        poll_options: PollOptions,
+        #[clap(flatten)]
+        program: ProgramIdOptions,
+        /// Optional commit hash to checkout
+        #[clap(short, long)]
+        commit_hash: Option<String>,
+        /// If the repo_url points to a repo that contains multiple programs, specify the name of the library name of the program to
+        /// build and verify
+        #[clap(long)]
+        library_name: Option<String>,
+        /// Verify several programs of a workspace, each as LIB_NAME:PROGRAM_ID, instead of a single
+        /// --program-id. One job is sent per program
+        #[clap(
+            long = "program",
+            value_name = "LIB_NAME:PROGRAM_ID",
+            conflicts_with_all = ["program_id", "program_keypair", "idl", "library_name"],
+            value_parser = parse_program_spec
+        )]
+        programs: Vec<(String, Pubkey)>,
+        /// If the program requires cargo build-bpf (instead of cargo build-sbf), as for an Anchor program, set this flag
+        #[clap(long, default_value = "false")]
+        bpf: bool,
+        /// Relative path to the root directory or the source code repository from which to build the program
+        #[clap(long, default_value = "", value_parser = parse_mount_path)]
+        mount_path: String,
+        /// Optionally specify a custom base docker image to use for building the program repository
+        #[clap(short, long)]
+        base_image: Option<String>,
+        #[clap(flatten)]
+        rpc: RpcOptions,
+        /// Arguments to pass to the underlying `cargo build-sbf` (or `cargo build-bpf`) command
         #[clap(required = false, last = true)]
         cargo_args: Vec<String>,
     },
+    /// Lists the docker images known to reproduce builds for each Solana version
+    ListImages {
+        /// Only list images for Solana versions starting with this version, e.g. 1.17
+        #[clap(long)]
+        solana_version: Option<String>,
+        /// Pull and run each listed image to report the rustc version it contains
+        #[clap(long, default_value = "false")]
+        rustc_version: bool,
+    },
+    /// Checks that docker, the default build image and the RPC endpoint are usable
+    Selfcheck {
+        #[clap(flatten)]
+        rpc: RpcOptions,
+    },
+    /// Resumes waiting for the result of a verification job on the remote server
+    ResumeRemoteJob {
+        /// The request ID of the job, as printed when the job was submitted
+        request_id: String,
+        /// URL of the remote verification server
+        #[clap(long, default_value = REMOTE_SERVER_URL, value_parser = parse_remote_url)]
+        remote_url: String,
+        #[clap(flatten)]
+        poll_options: PollOptions,
+    },
+    /// Cancels an in-progress verification job on the remote server
+    CancelRemoteJob {
+        /// The request ID of the job, as printed when the job was submitted
+        request_id: String,
+        /// URL of the remote verification server
+        #[clap(long, default_value = REMOTE_SERVER_URL, value_parser = parse_remote_url)]
+        remote_url: String,
+    },
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    // Handle SIGTERM and SIGINT gracefully by stopping the docker container
-    let mut signals = Signals::new([SIGTERM, SIGINT])?;
+async fn main() {
+    if let Err(e) = run().await {
+        // The full chain of causes is only listed with -v, otherwise it is joined into one line.
+        // Some errors, like those of the RPC client, already include their cause in the message.
+        if log::log_enabled!(log::Level::Info) {
+            eprintln!("Error: {:?}", e);
+        } else {
+            let mut message = e.to_string();
+            for cause in e.chain().skip(1).map(|cause| cause.to_string()) {
+                if !message.contains(&cause) {
+                    message = format!("{}: {}", message, cause);
+                }
+            }
+            eprintln!("Error: {}", message);
+        }
+        std::process::exit(1);
+    }
+}
+
+async fn run() -> anyhow::Result<()> {
     let mut container_id: Option<String> = None;
     let mut temp_dir: Option<String> = None;
     let caught_signal = Arc::new(AtomicBool::new(false));
 
-    let caught_signal_clone = caught_signal.clone();
-    let handle = signals.handle();
-    std::thread::spawn(move || {
-        for _ in signals.forever() {
-            caught_signal_clone.store(true, Ordering::Relaxed);
-            break;
-        }
-    });
+    // Handle SIGTERM and SIGINT gracefully by stopping the docker container
+    #[cfg(unix)]
+    let handle = {
+        let mut signals = Signals::new([SIGTERM, SIGINT])?;
+        let caught_signal_clone = caught_signal.clone();
+        let handle = signals.handle();
+        std::thread::spawn(move || {
+            if signals.forever().next().is_some() {
+                caught_signal_clone.store(true, Ordering::Relaxed);
+            }
+        });
+        handle
+    };
+    // Windows has no SIGTERM, so only Ctrl-C is handled there
+    #[cfg(not(unix))]
+    {
+        let caught_signal_clone = caught_signal.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                caught_signal_clone.store(true, Ordering::Relaxed);
+            }
+        });
+    }
+
+    let config = ConfigFile::load(config_path_arg().as_deref())?;
+    let args = match Arguments::from_arg_matches(
+        &with_config_defaults(Arguments::command(), &config).get_matches(),
+    ) {
+        Ok(args) => args,
+        Err(e) => e.exit(),
+    };
+    set_quiet(args.quiet);
+    set_json_output(args.output == OutputFormat::Json);
+    set_plain_output(
+        args.no_color || std::env::var_os("NO_COLOR").is_some() || !std::io::stdout().is_terminal(),
+    );
+    let log_level = match args.verbose {
+        _ if args.quiet => LevelFilter::Error,
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    let mut logger = env_logger::Builder::new();
+    logger.format_timestamp(None).format_target(false);
+    // RUST_LOG, e.g. to also log the RPC client, replaces the default levels without -v or -q
+    match std::env::var("RUST_LOG") {
+        Ok(filters) if args.verbose == 0 && !args.quiet => logger.parse_filters(&filters),
+        _ => logger
+            .filter_level(if args.quiet {
+                LevelFilter::Error
+            } else {
+                LevelFilter::Warn
+            })
+            .filter_module("solana_verify", log_level),
+    };
+    logger.init();
 
-    let args = Arguments::parse();
     let res = match args.subcommand {
         SubCommand::Build {
             // mount directory
             mount_directory,
             library_name,
-            base_image,
-            bpf: bpf_flag,
+            build_options,
+            require_digest,
+            print_command,
             cargo_args,
         } => build(
             mount_directory,
             library_name,
-            base_image,
-            bpf_flag,
+            &build_options,
+            require_digest,
+            print_command,
             cargo_args,
             &mut container_id,
-        ),
+        )
+        .map(|_| ()),
         SubCommand::VerifyFromImage {
             executable_path_in_image: executable_path,
             image,
-            program_id,
+            program,
+            buffer_address,
             current_dir,
-        } => verify_from_image(
-            executable_path,
-            image,
-            args.url,
-            program_id,
-            current_dir,
-            &mut temp_dir,
-            &mut container_id,
-        ),
-        SubCommand::GetExecutableHash { filepath } => {
-            let program_hash = get_file_hash(&filepath)?;
-            println!("{}", program_hash);
-            Ok(())
+            rpc,
+            expected_hash,
+            check_authority,
+            output_hash_file,
+        } => {
+            let against_expected_hash = expected_hash.is_some();
+            verify_from_image(
+                executable_path,
+                image,
+                args.url,
+                program.program_id()?,
+                buffer_address,
+                current_dir,
+                &rpc,
+                expected_hash,
+                check_authority,
+                &mut temp_dir,
+                &mut container_id,
+            )
+            .and_then(|result| {
+                if let Some(output_hash_file) = &output_hash_file {
+                    write_hash_file(output_hash_file, &result.executable_hash)?;
+                }
+                print_image_verification(
+                    &result,
+                    args.output,
+                    against_expected_hash,
+                    buffer_address,
+                )
+            })
+            .map_err(Into::into)
+        }
+        SubCommand::Hash { command } => hash(command, args.url, args.output),
+        // The names from before the hash subcommand, kept so existing scripts keep working
+        SubCommand::GetExecutableHash(file) => hash(HashCommand::File(file), args.url, args.output),
+        SubCommand::GetProgramHash(program) => {
+            hash(HashCommand::Program(program), args.url, args.output)
+        }
+        SubCommand::GetBufferHash(buffer) => {
+            hash(HashCommand::Buffer(buffer), args.url, args.output)
         }
-        SubCommand::GetBufferHash { buffer_address } => {
-            let buffer_hash = get_buffer_hash(args.url, buffer_address)?;
-            println!("{}", buffer_hash);
-            Ok(())
+        SubCommand::GetProgramDataHash(programdata) => {
+            hash(HashCommand::ProgramData(programdata), args.url, args.output)
         }
-        SubCommand::GetProgramHash { program_id } => {
-            let program_hash = get_program_hash(args.url, program_id)?;
-            println!("{}", program_hash);
-            Ok(())
+        SubCommand::DiffFiles { a, b, bytes } => {
+            let comparison = compare_files(&a, &b, bytes)?;
+            Ok(print_file_comparison(&comparison, &a, &b, args.output)?)
         }
         SubCommand::VerifyFromRepo {
             remote,
+            remote_url,
+            poll_options,
             mount_path,
             repo_url,
+            git_token,
             commit_hash,
-            program_id,
-            base_image,
+            program,
+            buffer_address,
+            compare_file,
             library_name,
-            bpf: bpf_flag,
+            build_options,
             cargo_args,
             current_dir,
+            keep_clone,
+            clone_options,
+            rpc,
+            expected_hash,
+            check_authority,
+            explain,
+            report,
+            sign_with,
+            output_hash_file,
+            output_dir,
         } => {
+            // Fail on a bad keypair before spending a build on it
+            let signer = sign_with
+                .map(|path| {
+                    read_keypair_file(&path)
+                        .map_err(|e| anyhow::anyhow!("Failed to read keypair {}: {}", path, e))
+                })
+                .transpose()?;
             verify_from_repo(
                 remote,
+                remote_url,
                 mount_path,
                 args.url,
                 repo_url,
+                git_token,
                 commit_hash,
-                program_id,
-                base_image,
+                program.program_id()?,
+                buffer_address,
+                compare_file,
                 library_name,
-                bpf_flag,
+                &build_options,
                 cargo_args,
                 current_dir,
+                keep_clone,
+                &clone_options,
+                args.output,
+                &rpc,
+                &poll_options,
+                expected_hash,
+                check_authority,
+                explain,
+                report,
+                signer.as_ref(),
+                output_hash_file,
+                output_dir,
                 &mut container_id,
                 &mut temp_dir,
             )
             .await
+            .map_err(Into::into)
         }
+        SubCommand::VerifyPendingUpgrade {
+            mount_path,
+            repo_url,
+            git_token,
+            commit_hash,
+            program,
+            buffer_address,
+            library_name,
+            build_options,
+            current_dir,
+            keep_clone,
+            clone_options,
+            rpc,
+            expected_hash,
+            explain,
+            report,
+            output_hash_file,
+            output_dir,
+            cargo_args,
+        } => verify_from_repo(
+            false,
+            REMOTE_SERVER_URL.to_string(),
+            mount_path,
+            args.url,
+            repo_url,
+            git_token,
+            commit_hash,
+            program.program_id()?,
+            Some(buffer_address),
+            None,
+            library_name,
+            &build_options,
+            cargo_args,
+            current_dir,
+            keep_clone,
+            &clone_options,
+            args.output,
+            &rpc,
+            &PollOptions::default(),
+            expected_hash,
+            None,
+            explain,
+            report,
+            None,
+            output_hash_file,
+            output_dir,
+            &mut container_id,
+            &mut temp_dir,
+        )
+        .await
+        .map_err(Into::into),
+        SubCommand::VerifyWorkspace {
+            mount_path,
+            repo_url,
+            git_token,
+            commit_hash,
+            programs,
+            build_options,
+            current_dir,
+            keep_clone,
+            clone_options,
+            rpc,
+            cargo_args,
+        } => verify_workspace(
+            mount_path,
+            args.url,
+            repo_url,
+            git_token,
+            commit_hash,
+            programs,
+            &build_options,
+            cargo_args,
+            current_dir,
+            keep_clone,
+            &clone_options,
+            args.output,
+            &rpc,
+            &mut container_id,
+            &mut temp_dir,
+        )
+        .map_err(Into::into),
+        SubCommand::VerifyRemote {
+            repo_url,
+            remote_url,
+            program,
+            commit_hash,
+            library_name,
+            programs,
+            bpf: bpf_flag,
+            mount_path,
+            base_image,
+            rpc,
+            cargo_args,
+            poll_options,
+        } => match program.resolve()? {
+            Some(program_id) => {
+                verify_remote(
+                    remote_url,
+                    args.url,
+                    &rpc,
+                    repo_url,
+                    commit_hash,
+                    program_id,
+                    library_name,
+                    bpf_flag,
+                    mount_path,
+                    base_image,
+                    cargo_args,
+                    &poll_options,
+                    args.output,
+                )
+                .await
+            }
+            None if programs.is_empty() => Err(anyhow::anyhow!(
+                "No program ID given. Pass --program-id, --program-keypair, --idl or --program"
+            )),
+            None => {
+                verify_remote_programs(
+                    remote_url,
+                    args.url,
+                    &rpc,
+                    repo_url,
+                    commit_hash,
+                    programs,
+                    bpf_flag,
+                    mount_path,
+                    base_image,
+                    cargo_args,
+                    &poll_options,
+                    args.output,
+                )
+                .await
+            }
+        },
+        SubCommand::ListImages {
+            solana_version,
+            rustc_version,
+        } => list_images(solana_version.as_deref(), rustc_version, args.output),
+        SubCommand::Selfcheck { rpc } => selfcheck(args.url, &rpc),
+        SubCommand::ResumeRemoteJob {
+            request_id,
+            remote_url,
+            poll_options,
+        } => resume_remote_job(&remote_url, &request_id, &poll_options, args.output).await,
+        SubCommand::CancelRemoteJob {
+            request_id,
+            remote_url,
+        } => cancel_remote_job(&remote_url, &request_id).await,
     };
 
     if caught_signal.load(Ordering::Relaxed) || res.is_err() {
-        if let Some(container_id) = container_id.clone().take() {
-            println!("Stopping container {}", container_id);
+        if let Some(container_id) = container_id.clone() {
+            print_status(format!("Stopping container {}", container_id));
             if std::process::Command::new("docker")
                 .args(["kill", &container_id])
                 .output()
                 .is_err()
             {
-                println!("Failed to close docker container");
+                eprintln!("Failed to close docker container");
             } else {
-                println!("Stopped container {}", container_id)
+                print_status(format!("Stopped container {}", container_id));
             }
         }
-        if let Some(temp_dir) = temp_dir.clone().take() {
-            println!("Removing temp dir {}", temp_dir);
-            if std::process::Command::new("rm")
-                .args(["-rf", &temp_dir])
-                .output()
-                .is_err()
-            {
-                println!("Failed to remove temp dir");
+        if let Some(temp_dir) = temp_dir.clone() {
+            print_status(format!("Removing temp dir {}", temp_dir));
+            if remove_temp_dir(&temp_dir).is_err() {
+                eprintln!("Failed to remove temp dir");
             } else {
-                println!("Removed temp dir {}", temp_dir);
+                print_status(format!("Removed temp dir {}", temp_dir));
             }
         }
     }
+    #[cfg(unix)]
     handle.close();
     res
 }
 
-pub fn get_client(url: Option<String>) -> RpcClient {
-    let config = match CONFIG_FILE.as_ref() {
-        Some(config_file) => Config::load(config_file).unwrap_or_else(|_| {
-            println!("Failed to load config file: {}", config_file);
-            Config::default()
-        }),
-        None => Config::default(),
-    };
-    let url = &get_network(&url.unwrap_or(config.json_rpc_url)).to_string();
-    RpcClient::new(url)
-}
-
-pub fn get_binary_hash(program_data: Vec<u8>) -> String {
-    let buffer = program_data
-        .into_iter()
-        .rev()
-        .skip_while(|&x| x == 0)
-        .collect::<Vec<_>>()
-        .into_iter()
-        .rev()
-        .collect::<Vec<_>>();
-    sha256::digest(&buffer[..])
-}
-
-pub fn get_file_hash(filepath: &str) -> Result<String, std::io::Error> {
-    let mut f = std::fs::File::open(filepath)?;
-    let metadata = std::fs::metadata(filepath)?;
-    let mut buffer = vec![0; metadata.len() as usize];
-    f.read_exact(&mut buffer)?;
-    Ok(get_binary_hash(buffer))
-}
-
-pub fn get_buffer_hash(url: Option<String>, buffer_address: Pubkey) -> anyhow::Result<String> {
-    let client = get_client(url);
-    let offset = UpgradeableLoaderState::size_of_buffer_metadata();
-    let account_data = client.get_account_data(&buffer_address)?[offset..].to_vec();
-    let program_hash = get_binary_hash(account_data);
-    Ok(program_hash)
-}
-
-pub fn get_program_hash(url: Option<String>, program_id: Pubkey) -> anyhow::Result<String> {
-    let client = get_client(url);
-    let program_buffer =
-        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0;
-    let offset = UpgradeableLoaderState::size_of_programdata_metadata();
-    let account_data = client.get_account_data(&program_buffer)?[offset..].to_vec();
-    let program_hash = get_binary_hash(account_data);
-    Ok(program_hash)
-}
-
-pub fn get_genesis_hash(url: Option<String>) -> anyhow::Result<String> {
-    let client = get_client(url);
-    let genesis_hash = client.get_genesis_hash()?;
-    Ok(genesis_hash.to_string())
-}
-
-
-pub fn get_docker_resource_limits() -> Option<(String, String)> {
-    let memory = std::env::var("SVB_DOCKER_MEMORY_LIMIT").ok();
-    let cpus = std::env::var("SVB_DOCKER_CPU_LIMIT").ok();
-    if memory.is_some() || cpus.is_some() {
-        println!("Using docker resource limits: memory: {:?}, cpus: {:?}", memory, cpus);
+/// Hashes program data read from a file or an account, returning the hash and the size that was
+/// hashed
+fn hash_program_data(program_data: &[u8], normalize_elf: bool) -> anyhow::Result<(String, usize)> {
+    let program_hash = if normalize_elf {
+        get_normalized_binary_hash(program_data)?
     } else {
-        // Print message to user that they can set these environment variables to limit docker resources
-        println!("No Docker resource limits are set.");
-        println!("You can set the SVB_DOCKER_MEMORY_LIMIT and SVB_DOCKER_CPU_LIMIT environment variables to limit Docker resources.");
-        println!("For example: SVB_DOCKER_MEMORY_LIMIT=2g SVB_DOCKER_CPU_LIMIT=2.");
-    }
-    memory.zip(cpus)
+        get_binary_hash(program_data)
+    };
+    Ok((program_hash, get_trimmed_size(program_data)))
 }
 
-pub fn build(
-    mount_directory: Option<String>,
-    library_name: Option<String>,
-    base_image: Option<String>,
-    bpf_flag: bool,
-    cargo_args: Vec<String>,
-    container_id_opt: &mut Option<String>,
-) -> anyhow::Result<()> {
-    let mount_path = mount_directory.unwrap_or(
-        std::env::current_dir()?
-            .as_os_str()
-            .to_str()
-            .ok_or_else(|| anyhow::Error::msg("Invalid path string"))?
-            .to_string(),
-    );
-    println!("Mounting path: {}", mount_path);
-
-    let lockfile = format!("{}/Cargo.lock", mount_path);
-    if !std::path::Path::new(&lockfile).exists() {
-        println!("Mount directory must contain a Cargo.lock file");
-        return Err(anyhow!(format!("No lockfile found at {}", lockfile)));
-    }
-
-    let build_command = if bpf_flag { "build-bpf" } else { "build-sbf" };
-
-    let (major, minor, patch) = get_pkg_version_from_cargo_lock("solana-program", &lockfile)?;
-
-    let mut solana_version: Option<String> = None;
-    let  image: String = base_image.unwrap_or_else(|| {
-        if bpf_flag {
-            // Use this for backwards compatibility with anchor verified builds
-            solana_version = Some("v1.13.5".to_string());
-            "projectserum/build@sha256:75b75eab447ebcca1f471c98583d9b5d82c4be122c470852a022afcf9c98bead".to_string()
-        } else if let Some(digest) = IMAGE_MAP.get(&(major, minor, patch)) {
-                println!("Found docker image for Solana version {}.{}.{}", major, minor, patch);
-                solana_version = Some(format!("v{}.{}.{}", major, minor, patch));
-                format!("ellipsislabs/solana@{}", digest)
+/// Prints the hash of a program binary and checks it against the expected hash, if one was given
+fn hash(command: HashCommand, url: Option<String>, output: OutputFormat) -> anyhow::Result<()> {
+    // Scripts read the hash from stdout, so everything else goes to stderr
+    set_status_on_stderr(true);
+    let ((program_hash, size), hash_options) = match command {
+        HashCommand::File(FileHashArgs {
+            filepath,
+            hash_options,
+        }) => {
+            let hash_and_size = if hash_options.normalize_elf {
+                hash_program_data(
+                    &std::fs::read(&filepath).map_err(|e| {
+                        anyhow::anyhow!("Failed to read executable {}: {}", filepath, e)
+                    })?,
+                    true,
+                )?
             } else {
-                println!("Unable to find docker image for Solana version {}.{}.{}", major, minor, patch);
-                let prev = IMAGE_MAP.range(..(major, minor, patch)).next_back();
-                let next = IMAGE_MAP.range((major, minor, patch)..).next();
-                let (version, digest) = if let Some((version, digest)) = prev {
-                    (version, digest)
-                } else if let Some((version, digest)) = next {
-                    (version, digest)
-                } else {
-                    println!("Unable to find backup docker image for Solana version {}.{}.{}", major, minor, patch);
-                    std::process::exit(1);
-                };
-                println!("Using backup docker image for Solana version {}.{}.{}", version.0, version.1, version.2);
-                solana_version = Some(format!("v{}.{}.{}", version.0, version.1, version.2));
-                format!("ellipsislabs/solana@{}", digest)
+                get_file_hash_and_size(&filepath)?
+            };
+            (hash_and_size, hash_options)
+        }
+        HashCommand::Program(ProgramHashArgs {
+            program_id,
+            rpc,
+            all_loaders,
+            hash_options,
+        }) => {
+            let client = get_client(url, &rpc);
+            let (loader, program_data) =
+                on_chain_program_data(&RpcFetcher::new(&client, &rpc), program_id)?;
+            check_min_slot(&client, &rpc)?;
+            check_at_slot(&client, program_id, &rpc)?;
+            if all_loaders {
+                print_status(format!("Loader: {}", loader));
             }
-    });
-
-    let mut manifest_path = None;
-
-    let relative_build_path = std::process::Command::new("find")
-        .args([&mount_path, "-name", "Cargo.toml"])
-        .output()
-        .map_err(|e| {
-            anyhow::format_err!(
-                "Failed to find Cargo.toml files in root directory: {}",
-                e.to_string()
+            (
+                hash_program_data(&program_data, hash_options.normalize_elf)?,
+                hash_options,
             )
-        })
-        .and_then(|output| {
-            for p in String::from_utf8(output.stdout)?.split("\n") {
-                match get_lib_name_from_cargo_toml(p) {
-                    Ok(name) => {
-                        if name == library_name.clone().unwrap_or_default() {
-                            manifest_path = Some(p.to_string().replace(&mount_path, ""));
-                            return Ok(p
-                                .to_string()
-                                .replace("Cargo.toml", "")
-                                .replace(&mount_path, ""));
-                        }
-                    }
-                    Err(_) => {
-                        continue;
-                    }
-                }
-            }
-            Err(anyhow!("No Cargo.toml files found"))
-        })
-        .unwrap_or_else(|_| "".to_string());
-
-    let workdir = std::process::Command::new("docker")
-        .args(["run", "--rm", &image, "pwd"])
-        .stderr(Stdio::inherit())
-        .output()
-        .map_err(|e| anyhow::format_err!("Failed to get workdir: {}", e.to_string()))
-        .and_then(|output| parse_output(output.stdout))?;
-
-    println!("Workdir: {}", workdir);
-
-    let build_path = format!("{}/{}", workdir, relative_build_path);
-    println!("Building program at {}", build_path);
-
-    let manifest_path_filter = manifest_path
-        .clone()
-        .map(|m| vec!["--manifest-path".to_string(), format!("{}/{}", workdir, m)])
-        .unwrap_or_else(Vec::new);
-
-    if manifest_path.is_some() {
-        println!(
-            "Building manifest path: {}/{}",
-            workdir,
-            manifest_path.unwrap()
-        );
-    }
-
-    // change directory to program/build dir
-    let mount_params = format!("{}:{}", mount_path, workdir);
-    let container_id = {
-        let mut cmd = std::process::Command::new("docker");
-            cmd.args(["run", "--rm", "-v", &mount_params, "-dit"]);
-            cmd.stderr(Stdio::inherit());
-
-        if let Some((memory_limit, cpu_limit)) = get_docker_resource_limits() {
-            cmd.arg("--memory").arg(memory_limit).arg("--cpus").arg(cpu_limit);
         }
-
-        let output = cmd
-            .args([&image, "bash"])
-            .output()
-            .map_err(|e| anyhow!("Docker build failed: {}", e.to_string()))?;
-
-        parse_output(output.stdout)?
-    };
-
-    // Set the container id so we can kill it later if the process is interrupted
-    container_id_opt.replace(container_id.clone());
-
-    // Solana v1.17 uses Rust 1.73, which defaults to the sparse registry, making
-    // this fetch unnecessary, but requires us to omit the "frozen" argument
-    let locked_args = if major == 1 && minor < 17 {
-        // First, we resolve the dependencies and cache them in the Docker container
-        // ARM processors running Linux have a bug where the build fails if the dependencies are not preloaded.
-        // Running the build without the pre-fetch will cause the container to run out of memory.
-        // This is a workaround for that issue.
-        std::process::Command::new("docker")
-            .args(["exec", &container_id])
-            .args([
-                "cargo",
-                "--config",
-                "net.git-fetch-with-cli=true",
-                "fetch",
-                "--locked",
-            ])
-            .stderr(Stdio::inherit())
-            .stdout(Stdio::inherit())
-            .output()?;
-        println!("Finished fetching build dependencies");
-
-        ["--frozen", "--locked"].as_slice()
-    } else {
-        // To be totally safe, force the build to use the sparse registry
-        [
-            "--config",
-            "registries.crates-io.protocol=\"sparse\"",
-            "--locked",
-        ]
-        .as_slice()
-    };
-
-    std::process::Command::new("docker")
-        .args(["exec", "-w", &build_path, &container_id])
-        .args(["cargo", build_command])
-        .args(["--"])
-        .args(locked_args)
-        .args(manifest_path_filter)
-        .args(cargo_args)
-        .stderr(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .output()?;
-
-    println!("Finished building program");
-    println!("Program Solana version: v{}.{}.{}", major, minor, patch);
-
-    if let Some(solana_version) = solana_version {
-        println!("Docker image Solana version: {}", solana_version);
-    }
-
-    if let Some(program_name) = library_name {
-        let executable_path = std::process::Command::new("find")
-            .args([
-                &format!("{}/target/deploy", mount_path),
-                "-name",
-                &format!("{}.so", program_name),
-            ])
-            .output()
-            .map_err(|e| anyhow!("Failed to find program: {}", e.to_string()))
-            .and_then(|output| parse_output(output.stdout))?;
-        let executable_hash = get_file_hash(&executable_path)?;
-        println!("{}", executable_hash);
-    }
-    std::process::Command::new("docker")
-        .args(["kill", &container_id])
-        .output()?;
-    Ok(())
-}
-
-pub fn verify_from_image(
-    executable_path: String,
-    image: String,
-    network: Option<String>,
-    program_id: Pubkey,
-    current_dir: bool,
-    temp_dir: &mut Option<String>,
-    container_id_opt: &mut Option<String>,
-) -> anyhow::Result<()> {
-    println!(
-        "Verifying image: {:?}, on network {:?} against program ID {}",
-        image, network, program_id
-    );
-    println!("Executable path in container: {:?}", executable_path);
-    println!(" ");
-
-    let workdir = std::process::Command::new("docker")
-        .args(["run", "--rm", &image, "pwd"])
-        .stderr(Stdio::inherit())
-        .output()
-        .map_err(|e| anyhow::format_err!("Failed to get workdir: {}", e.to_string()))
-        .and_then(|output| parse_output(output.stdout))?;
-
-    println!("Workdir: {}", workdir);
-
-
-    let container_id = {
-        let mut cmd = std::process::Command::new("docker");
-            cmd.args(["run", "--rm", "-dit"]);
-            cmd.stderr(Stdio::inherit());
-
-        if let Some((memory_limit, cpu_limit)) = get_docker_resource_limits() {
-            cmd.arg("--memory").arg(memory_limit).arg("--cpus").arg(cpu_limit);
+        HashCommand::Programs(ProgramHashesArgs {
+            program_ids,
+            rpc,
+            jobs,
+            show_size,
+        }) => {
+            return hash_programs(url, &program_ids, &rpc, jobs as usize, show_size, output);
         }
-
-        let output = cmd
-            .args([&image])
-            .output()
-            .map_err(|e| anyhow!("Docker build failed: {}", e.to_string()))?;
-        parse_output(output.stdout)?
-    };
-
-    container_id_opt.replace(container_id.clone());
-
-    let uuid = Uuid::new_v4().to_string();
-
-    // Create a temporary directory to clone the repo into
-    let verify_dir = if current_dir {
-        format!(
-            "{}/.{}",
-            std::env::current_dir()?
-                .as_os_str()
-                .to_str()
-                .ok_or_else(|| anyhow::Error::msg("Invalid path string"))?,
-            uuid.clone()
-        )
-    } else {
-        "/tmp".to_string()
+        HashCommand::Buffer(BufferHashArgs {
+            buffer_address,
+            rpc,
+            hash_options,
+        }) => (
+            hash_program_data(
+                &get_buffer_data(url, buffer_address, &rpc)?,
+                hash_options.normalize_elf,
+            )?,
+            hash_options,
+        ),
+        HashCommand::ProgramData(ProgramDataHashArgs {
+            programdata_address,
+            rpc,
+            hash_options,
+        }) => (
+            hash_program_data(
+                &get_programdata_data(url, programdata_address, &rpc)?,
+                hash_options.normalize_elf,
+            )?,
+            hash_options,
+        ),
     };
-
-    temp_dir.replace(verify_dir.clone());
-
-    let program_filepath = format!("{}/program.so", verify_dir);
-    std::process::Command::new("docker")
-        .args([
-            "cp",
-            format!("{}:{}/{}", container_id, workdir, executable_path).as_str(),
-            program_filepath.as_str(),
-        ])
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .output()
-        .map_err(|e| anyhow::format_err!("Failed to copy executable file {}", e.to_string()))?;
-
-    let executable_hash: String = get_file_hash(program_filepath.as_str())?;
-    let client = get_client(network);
-    let program_buffer =
-        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0;
-    let offset = UpgradeableLoaderState::size_of_programdata_metadata();
-    let account_data = &client.get_account_data(&program_buffer)?[offset..];
-    let program_hash = get_binary_hash(account_data.to_vec());
-    println!("Executable hash: {}", executable_hash);
-    println!("Program hash: {}", program_hash);
-
-    // Cleanup docker and rm file
-    std::process::Command::new("docker")
-        .args(["kill", container_id.as_str()])
-        .stderr(Stdio::inherit())
-        .output()
-        .map_err(|e| anyhow::format_err!("Docker kill failed: {}", e.to_string()))?;
-
-    std::process::Command::new("rm")
-        .args([program_filepath])
-        .stderr(Stdio::inherit())
-        .output()
-        .map_err(|e| {
-            anyhow::format_err!("Failed to remove temp program file: {}", e.to_string())
-        })?;
-
-    if program_hash != executable_hash {
-        println!("Executable hash mismatch");
-        return Err(anyhow::Error::msg("Executable hash mismatch"));
-    } else {
-        println!("Executable matches on-chain program data ✅");
+    if hash_options.normalize_elf {
+        print_status("Normalized hash, only comparable with other normalized hashes:");
     }
-    Ok(())
-}
-
-#[allow(clippy::too_many_arguments)]
-pub async fn verify_from_repo(
-    remote: bool,
-    relative_mount_path: String,
-    connection_url: Option<String>,
-    repo_url: String,
-    commit_hash: Option<String>,
-    program_id: Pubkey,
-    base_image: Option<String>,
-    library_name_opt: Option<String>,
-    bpf_flag: bool,
-    cargo_args: Vec<String>,
-    current_dir: bool,
-    container_id_opt: &mut Option<String>,
-    temp_dir_opt: &mut Option<String>,
-) -> anyhow::Result<()> {
-    if remote {
-        let genesis_hash = get_genesis_hash(connection_url)?;
-        if genesis_hash != MAINNET_GENESIS_HASH {
-            return Err(anyhow!("Remote verification only works with mainnet. Please omit the --remote flag to verify locally."));
-        }
-
-        println!("Sending verify command to remote machine...");
-        send_job_to_remote(
-            &repo_url,
-            &commit_hash,
-            &program_id,
-            &library_name_opt,
-            bpf_flag,
-            relative_mount_path,
-            base_image,
-            cargo_args,
-        )
-        .await?;
-        return Ok(());
+    println!("{}", program_hash);
+    // The size was asked for, so it is printed even with --quiet, but not on stdout either
+    if hash_options.show_size {
+        eprintln!("Size: {} bytes (without zero padding)", size);
     }
-
-    // Get source code from repo_url
-    let base_name = std::process::Command::new("basename")
-        .arg(&repo_url)
-        .output()
-        .map_err(|e| anyhow!("Failed to get basename of repo_url: {:?}", e))
-        .and_then(|output| parse_output(output.stdout))?;
-
-    let uuid = Uuid::new_v4().to_string();
-
-    // Create a temporary directory to clone the repo into
-    let verify_dir = if current_dir {
-        format!(
-            "{}/.{}",
-            std::env::current_dir()?
-                .as_os_str()
-                .to_str()
-                .ok_or_else(|| anyhow::Error::msg("Invalid path string"))?,
-            uuid.clone()
-        )
-    } else {
-        format!("/tmp/solana-verify/{}", uuid)
-    };
-
-    temp_dir_opt.replace(verify_dir.clone());
-
-    let verify_tmp_root_path = format!("{}/{}", verify_dir, base_name);
-    println!("Cloning repo into: {}", verify_tmp_root_path);
-
-    std::process::Command::new("git")
-        .args(["clone", &repo_url, &verify_tmp_root_path])
-        .stdout(Stdio::inherit())
-        .output()?;
-
-    // Checkout a specific commit hash, if provided
-    if let Some(commit_hash) = commit_hash {
-        let result = std::process::Command::new("git")
-            .args(["-C", &verify_tmp_root_path])
-            .args(["checkout", &commit_hash])
-            .output()
-            .map_err(|e| anyhow!("Failed to checkout commit hash: {:?}", e));
-        if result.is_ok() {
-            println!("Checked out commit hash: {}", commit_hash);
-        } else {
-            std::process::Command::new("rm")
-                .args(["-rf", verify_dir.as_str()])
-                .output()?;
-            Err(anyhow!("Encountered error in git setup: {:?}", result))?;
-        }
+    if let Some(output_hash_file) = &hash_options.output_hash_file {
+        write_hash_file(output_hash_file, &program_hash)?;
     }
-
-    // Get the absolute build path to the solana program directory to build inside docker
-    let mount_path = PathBuf::from(verify_tmp_root_path.clone()).join(relative_mount_path);
-    println!("Build path: {:?}", mount_path);
-
-    let library_name = match library_name_opt {
-        Some(p) => p,
-        None => { 
-                std::process::Command::new("find")
-                    .args([mount_path.to_str().unwrap(), "-name", "Cargo.toml"])
-                    .output()
-                    .map_err(|e| {
-                        anyhow::format_err!(
-                            "Failed to find Cargo.toml files in root directory: {}",
-                            e.to_string()
-                        )
-                    })
-                    .and_then(|output| {
-                        let mut options = vec![];
-                        for path in String::from_utf8(output.stdout)?.split("\n") {
-                            match get_lib_name_from_cargo_toml(path) {
-                                Ok(name) => {
-                                    options.push(name);
-                                }
-                                Err(_) => {
-                                    continue;
-                                }
-                            }
-                        }
-                        if options.len() != 1 {
-                            println!(
-                                "Found multiple possible targets in root directory: {:?}",
-                                options
-                            );
-                            println!(
-                                "Please explicitly specify the target with the --package-name <name> option",
-                            );
-                            Err(anyhow::format_err!(
-                                "Failed to find unique Cargo.toml file in root directory"
-                            ))
-                        } else {
-                            Ok(options[0].clone())
-                        }
-                    })?
-        }
-    };
-    println!("Verifying program: {}", library_name);
-
-    let result = build_and_verify_repo(
-        mount_path.to_str().unwrap().to_string(),
-        base_image,
-        bpf_flag,
-        library_name,
-        connection_url,
-        program_id,
-        cargo_args,
-        container_id_opt,
-    );
-
-    // Cleanup no matter the result
-    std::process::Command::new("rm")
-        .args(["-rf", &verify_dir])
-        .output()?;
-
-    // Compare hashes or return error
-    if let Ok((build_hash, program_hash)) = result {
-        println!("Executable Program Hash from repo: {}", build_hash);
-        println!("On-chain Program Hash: {}", program_hash);
-
-        if build_hash == program_hash {
-            println!("Program hash matches ✅");
-        } else {
-            println!("Program hashes do not match ❌");
-        }
-
-        Ok(())
-    } else {
-        Err(anyhow!("Error verifying program. {:?}", result))
-    }
-}
-
-#[allow(clippy::too_many_arguments)]
-pub fn build_and_verify_repo(
-    mount_path: String,
-    base_image: Option<String>,
-    bpf_flag: bool,
-    library_name: String,
-    connection_url: Option<String>,
-    program_id: Pubkey,
-    cargo_args: Vec<String>,
-    container_id_opt: &mut Option<String>,
-) -> anyhow::Result<(String, String)> {
-    // Build the code using the docker container
-    let executable_filename = format!("{}.so", &library_name);
-    build(
-        Some(mount_path.clone()),
-        Some(library_name),
-        base_image,
-        bpf_flag,
-        cargo_args,
-        container_id_opt,
-    )?;
-
-    // Get the hash of the build
-    let executable_path = std::process::Command::new("find")
-        .args([
-            &format!("{}/target/deploy", mount_path),
-            "-name",
-            executable_filename.as_str(),
-        ])
-        .output()
-        .map_err(|e| anyhow::format_err!("Failed to find executable file {}", e.to_string()))
-        .and_then(|output| parse_output(output.stdout))?;
-    println!("Executable file found at path: {:?}", executable_path);
-    let build_hash = get_file_hash(&executable_path)?;
-
-    // Get the hash of the deployed program
-    println!(
-        "Fetching on-chain program data for program ID: {}",
-        program_id,
-    );
-    let program_hash = get_program_hash(connection_url, program_id)?;
-
-    Ok((build_hash, program_hash))
-}
-
-pub fn parse_output(output: Vec<u8>) -> anyhow::Result<String> {
-    let parsed_output = String::from_utf8(output)?
-        .strip_suffix("\n")
-        .ok_or_else(|| anyhow!("Failed to parse output"))?
-        .to_string();
-    Ok(parsed_output)
-}
-
-pub fn get_pkg_version_from_cargo_lock(
-    package_name: &str,
-    cargo_lock_file: &str,
-) -> anyhow::Result<(u32, u32, u32)> {
-    let lockfile = Lockfile::load(cargo_lock_file)?;
-    let res = lockfile
-        .packages
-        .iter()
-        .filter(|pkg| pkg.name.to_string() == *package_name)
-        .filter_map(|pkg| {
-            let version = pkg.version.clone().to_string();
-            let version_parts: Vec<&str> = version.split(".").collect();
-            if version_parts.len() == 3 {
-                let major = version_parts[0].parse::<u32>().unwrap_or(0);
-                let minor = version_parts[1].parse::<u32>().unwrap_or(0);
-                let patch = version_parts[2].parse::<u32>().unwrap_or(0);
-                return Some((major, minor, patch));
-            }
-            None
-        })
-        .next()
-        .ok_or_else(|| anyhow!("Failed to parse solana-program version from Cargo.lock"))?;
-    Ok(res)
-}
-
-pub fn get_lib_name_from_cargo_toml(cargo_toml_file: &str) -> anyhow::Result<String> {
-    let manifest = Manifest::from_path(cargo_toml_file)?;
-    let lib = manifest
-        .lib
-        .ok_or_else(|| anyhow!("Failed to parse lib from Cargo.toml"))?;
-    lib.name
-        .ok_or_else(|| anyhow!("Failed to parse lib name from Cargo.toml"))
-}
-
-pub fn get_pkg_name_from_cargo_toml(cargo_toml_file: &str) -> Option<String> {
-    let manifest = Manifest::from_path(cargo_toml_file).ok()?;
-    let pkg = manifest.package?;
-    Some(pkg.name)
+    Ok(check_expected_hash(
+        &program_hash,
+        hash_options.expected_hash.as_deref(),
+    )?)
 }
@@ -0,0 +1,59 @@
+use serde::Serialize;
+use solana_sdk::signature::{Keypair, Signer};
+
+use crate::Toolchain;
+
+/// A record of a verification that can be published alongside a release as proof that the
+/// deployed program was built from the given commit
+#[derive(Debug, Serialize)]
+pub struct VerificationReport {
+    pub repo_url: String,
+    pub commit_hash: Option<String>,
+    pub program_id: String,
+    /// Set when the build was compared against a buffer instead of the deployed program
+    pub buffer_address: Option<String>,
+    /// The build image pinned to its digest
+    pub base_image: Option<String>,
+    pub toolchain: Option<Toolchain>,
+    /// The build as a single shell command, with the mount path, cargo arguments and image
+    pub build_command: Option<String>,
+    pub executable_hash: String,
+    pub on_chain_hash: String,
+    pub verified: bool,
+    /// When the verification ran, in RFC 3339 format
+    pub timestamp: String,
+}
+
+/// An ed25519 signature over the exact bytes of a report file, so that anyone with the signer's
+/// public key can check that the report is unchanged and was published by the signer
+#[derive(Debug, Serialize)]
+pub struct ReportAttestation {
+    pub signer: String,
+    pub signature: String,
+}
+
+impl VerificationReport {
+    /// Writes the report to `path` as pretty-printed JSON
+    pub fn write(&self, path: &str) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n")
+            .map_err(|e| anyhow::anyhow!("Failed to write report to {}: {}", path, e))
+    }
+}
+
+impl ReportAttestation {
+    /// Signs the report written to `report_path` and writes the attestation next to it, to
+    /// `<report_path>.sig`
+    pub fn sign(report_path: &str, keypair: &Keypair) -> anyhow::Result<Self> {
+        let report = std::fs::read(report_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read report {}: {}", report_path, e))?;
+        let attestation = Self {
+            signer: keypair.pubkey().to_string(),
+            signature: keypair.sign_message(&report).to_string(),
+        };
+        let path = format!("{}.sig", report_path);
+        std::fs::write(&path, serde_json::to_string_pretty(&attestation)? + "\n")
+            .map_err(|e| anyhow::anyhow!("Failed to write attestation to {}: {}", path, e))?;
+        Ok(attestation)
+    }
+}
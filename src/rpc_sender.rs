@@ -0,0 +1,113 @@
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+use solana_client::{
+    client_error::Result,
+    rpc_request::{RpcError, RpcRequest, RpcResponseErrorData},
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+/// Sends RPC requests over HTTP with extra headers, e.g. the API key of an RPC provider. The
+/// sender of `solana_client` can't be given custom headers.
+pub struct HeaderSender {
+    client: reqwest::Client,
+    url: String,
+    request_id: AtomicU64,
+    stats: RwLock<RpcTransportStats>,
+}
+
+#[derive(Deserialize)]
+struct RpcErrorObject {
+    code: i64,
+    message: String,
+}
+
+impl HeaderSender {
+    pub fn new(url: String, headers: &[(String, String)], timeout: Duration) -> Self {
+        let mut default_headers = HeaderMap::new();
+        for (key, value) in headers {
+            // The headers were validated when parsing the arguments
+            if let (Ok(key), Ok(value)) = (
+                HeaderName::from_bytes(key.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                default_headers.append(key, value);
+            }
+        }
+        let client = reqwest::Client::builder()
+            .default_headers(default_headers)
+            .timeout(timeout)
+            .pool_idle_timeout(timeout)
+            .build()
+            .expect("build rpc client");
+        Self {
+            client,
+            url,
+            request_id: AtomicU64::new(0),
+            stats: RwLock::new(RpcTransportStats::default()),
+        }
+    }
+}
+
+#[async_trait]
+impl RpcSender for HeaderSender {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let start = Instant::now();
+        let request_id = self.request_id.fetch_add(1, Ordering::Relaxed);
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request_id,
+                "method": request.to_string(),
+                "params": params,
+            }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        {
+            let mut stats = self.stats.write().unwrap();
+            stats.request_count += 1;
+            stats.elapsed_time += start.elapsed();
+        }
+
+        let mut json = response?.json::<serde_json::Value>().await?;
+        if json["error"].is_object() {
+            return Err(
+                match serde_json::from_value::<RpcErrorObject>(json["error"].clone()) {
+                    Ok(error) => RpcError::RpcResponseError {
+                        code: error.code,
+                        message: error.message,
+                        data: RpcResponseErrorData::Empty,
+                    },
+                    Err(e) => RpcError::RpcRequestError(format!(
+                        "Failed to deserialize RPC error response: {} [{}]",
+                        json["error"], e
+                    )),
+                }
+                .into(),
+            );
+        }
+        Ok(json["result"].take())
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.stats.read().unwrap().clone()
+    }
+
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+}
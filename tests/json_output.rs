@@ -0,0 +1,129 @@
+//! With `--output json`, stdout must hold nothing but the JSON result so that it can be piped
+//! into a parser. Docker is replaced with a script that "builds" a fixed executable.
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+
+const FAKE_DOCKER: &str = r#"#!/bin/sh
+state=$(dirname "$0")
+case "$1" in
+  image) echo "solanafoundation/solana-verifiable-build@sha256:0000000000000000000000000000000000000000000000000000000000000000" ;;
+  run)
+    case "$*" in
+      *" pwd") echo /work ;;
+      *-dit*)
+        for arg in "$@"; do
+          case "$arg" in *:/work) echo "${arg%:/work}" > "$state/mount" ;; esac
+        done
+        echo container ;;
+    esac ;;
+  exec)
+    case "$*" in
+      *build-sbf*)
+        deploy="$(cat "$state/mount")/target/deploy"
+        mkdir -p "$deploy"
+        cp "$state/program.so" "$deploy/program.so"
+        echo "Finished release target(s)" ;;
+      *--version) echo "1.17.3" ;;
+    esac ;;
+esac
+"#;
+
+const REPO_URL: &str = "https://example.com/program.git";
+
+/// The smallest ELF that passes as an SBF executable: a header for the eBPF machine
+fn sbf_executable() -> Vec<u8> {
+    let mut elf = vec![0; 64];
+    elf[..4].copy_from_slice(b"\x7fELF");
+    elf[4] = 2; // 64-bit
+    elf[5] = 1; // little endian
+    elf[6] = 1; // ELF version
+    elf[16] = 3; // shared object
+    elf[18..20].copy_from_slice(&247u16.to_le_bytes());
+    elf[20..24].copy_from_slice(&1u32.to_le_bytes());
+    elf[52..54].copy_from_slice(&64u16.to_le_bytes());
+    elf[54..56].copy_from_slice(&56u16.to_le_bytes());
+    elf[58..60].copy_from_slice(&64u16.to_le_bytes());
+    elf
+}
+
+fn git(repo: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+#[test]
+fn verify_from_repo_prints_a_single_json_value() {
+    let dir = std::env::temp_dir().join(format!("solana-verify-json-{}", std::process::id()));
+    let bin = dir.join("bin");
+    let repo = dir.join("repo");
+    std::fs::create_dir_all(&bin).unwrap();
+    std::fs::create_dir_all(&repo).unwrap();
+
+    let docker = bin.join("docker");
+    std::fs::write(&docker, FAKE_DOCKER).unwrap();
+    std::fs::set_permissions(&docker, std::fs::Permissions::from_mode(0o755)).unwrap();
+    std::fs::write(bin.join("program.so"), sbf_executable()).unwrap();
+    std::fs::write(dir.join("on-chain.so"), sbf_executable()).unwrap();
+
+    std::fs::write(
+        repo.join("Cargo.toml"),
+        "[package]\nname = \"program\"\nversion = \"0.1.0\"\n\n[lib]\nname = \"program\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        repo.join("Cargo.lock"),
+        "version = 3\n\n[[package]]\nname = \"solana-program\"\nversion = \"1.17.3\"\n",
+    )
+    .unwrap();
+    git(&repo, &["init", "-q"]);
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "-q", "-m", "program"]);
+    // Local paths aren't accepted as repo URLs, so git is told to find the remote one here
+    std::fs::write(
+        dir.join(".gitconfig"),
+        format!(
+            "[url \"file://{}\"]\n\tinsteadOf = {}\n",
+            repo.display(),
+            REPO_URL
+        ),
+    )
+    .unwrap();
+
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap());
+    let output = Command::new(env!("CARGO_BIN_EXE_solana-verify"))
+        .env("PATH", path)
+        .env("HOME", &dir)
+        .args(["--url", "localhost", "--output", "json", "verify-from-repo"])
+        .arg(REPO_URL)
+        .args(["--program-id", "11111111111111111111111111111114"])
+        .arg("--compare-file")
+        .arg(dir.join("on-chain.so"))
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "verification failed: {}", stderr);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut values = serde_json::Deserializer::from_str(&stdout).into_iter::<serde_json::Value>();
+    let result = values
+        .next()
+        .expect("nothing was printed to stdout")
+        .expect("stdout is not JSON");
+    assert!(
+        values.next().is_none(),
+        "more than the result on stdout: {}",
+        stdout
+    );
+    assert_eq!(result["verified"], true);
+}